@@ -2,20 +2,62 @@ use proc_macro::{TokenStream};
 use proc_macro2::Span;
 use syn::punctuated::Punctuated;
 use syn::parse::{Parse, ParseStream, Result};
-use syn::{Ident,Token, parse_macro_input, LitInt, ItemFn};
+use syn::{Ident,Token, parse_macro_input, LitInt, LitStr, ItemFn, FnArg, Type, Expr};
 use quote::quote;
 
+/// The parse and solve phases of a part, as functions or closures in the scope of the task
+struct Phases{
+    parse: Expr,
+    solve: Expr,
+}
+
 struct Args{
     year: u32,
     day: u32,
     part: Option<u32>,
+    variant: Option<LitStr>,
+    phases: Option<Phases>,
 }
 
 impl Parse for Args{
     fn parse(input: ParseStream) -> Result<Self>{
-        let vars = Punctuated::<LitInt, Token![,]>::parse_terminated(input)?;
+        // Literals come first, then the phases as `parse = ..., solve = ...`
+        let mut vars = Vec::new();
+        let (mut parse, mut solve) = (None, None);
+        for arg in Punctuated::<Expr, Token![,]>::parse_terminated(input)?{
+            match arg{
+                Expr::Lit(lit) if parse.is_none() && solve.is_none() => vars.push(lit.lit),
+                Expr::Assign(assign) => match &*assign.left{
+                    Expr::Path(path) if path.path.is_ident("parse") && parse.is_none() => parse = Some(*assign.right),
+                    Expr::Path(path) if path.path.is_ident("solve") && solve.is_none() => solve = Some(*assign.right),
+                    left => return Err(syn::Error::new_spanned(left, "Expected parse or solve, once each")),
+                },
+                arg => return Err(syn::Error::new_spanned(arg, "Expected a literal, or parse = ... or solve = ... after them")),
+            }
+        }
+        let phases = match (parse, solve){
+            (Some(parse), Some(solve)) => Some(Phases{ parse, solve }),
+            (None, None) => None,
+            _ => return Err(syn::Error::new(input.span(), "Expected both parse and solve, or neither")),
+        };
+
+        // An optional variant name for alternative implementations of the same part comes last
+        let variant = match vars.last(){
+            Some(syn::Lit::Str(variant)) => {
+                let variant = variant.clone();
+                vars.pop();
+                Some(variant)
+            },
+            _ => None,
+        };
+
+        let vars = vars.into_iter().map(|var| match var{
+            syn::Lit::Int(var) => Ok(var),
+            var => Err(syn::Error::new(var.span(), "Expected an integer")),
+        }).collect::<Result<Vec<LitInt>>>()?;
+
         if vars.len() < 2 || vars.len() > 3{
-            return Err(syn::Error::new(input.span(), "Expected 2 or 3 arguments (year, day, [part])"))
+            return Err(syn::Error::new(input.span(), "Expected 2 or 3 arguments (year, day, [part]) and an optional variant name"))
         }
 
         let year = vars[0].base10_parse::<u32>().unwrap();
@@ -27,11 +69,35 @@ impl Parse for Args{
             None
         };
 
-        Ok(Args { year, day, part})
+        Ok(Args { year, day, part, variant, phases })
     }
 }
 
 
+/// How a task wants its input, told apart by the type of its first parameter
+enum InputKind{
+    /// `&str`, the whole input as text
+    Text,
+    /// `&[u8]`, the whole input as bytes
+    Bytes,
+    /// Anything else is handed a reader over the bytes, for tasks taking a `BufRead`
+    Reader,
+}
+
+fn input_kind(function: &ItemFn) -> InputKind{
+    let Some(FnArg::Typed(argument)) = function.sig.inputs.first() else{
+        return InputKind::Reader;
+    };
+    let Type::Reference(reference) = &*argument.ty else{
+        return InputKind::Reader;
+    };
+    match &*reference.elem{
+        Type::Path(path) if path.path.is_ident("str") => InputKind::Text,
+        Type::Slice(slice) if matches!(&*slice.elem, Type::Path(path) if path.path.is_ident("u8")) => InputKind::Bytes,
+        _ => InputKind::Reader,
+    }
+}
+
 #[proc_macro_attribute]
 pub fn aoc_task(args: TokenStream, input: TokenStream) -> TokenStream{
     let input = parse_macro_input!(input as ItemFn);
@@ -40,8 +106,50 @@ pub fn aoc_task(args: TokenStream, input: TokenStream) -> TokenStream{
     let year = args.year;
     let day = args.day;
     let part = args.part.unwrap_or(1);
+    let variant = match args.variant{
+        Some(variant) => quote!{ Some(#variant) },
+        None => quote!{ None },
+    };
 
     let task_function_name = input.sig.ident.clone();
+    let input_kind = input_kind(&input);
+    let phases = match (&args.phases, &input_kind){
+        (None, _) => quote!{},
+        (Some(_), InputKind::Reader) => {
+            return syn::Error::new_spanned(&input.sig, "Only tasks taking &str or &[u8] can be split into phases").to_compile_error().into();
+        },
+        (Some(Phases{ parse, solve }), kind) => {
+            // The parser is handed the input the same way as the task itself
+            let text = match kind{
+                InputKind::Text => quote!{ let input = std::str::from_utf8(input).ok()?; },
+                _ => quote!{},
+            };
+            quote!{
+                fn phases(&self) -> Option<crate::phases::Parse>{
+                    use super::*;
+                    let parse: crate::phases::Parse = |input|{
+                        #text
+                        let model = crate::phases::Parsed::parsed((#parse)(input))?;
+                        Some(crate::phases::solve(move || (#solve)(&model)))
+                    };
+                    Some(parse)
+                }
+            }
+        },
+    };
+    let invoke = match input_kind{
+        InputKind::Text => quote!{
+            match std::str::from_utf8(input){
+                Ok(text) => crate::run(super::#task_function_name(text), output),
+                Err(error) => writeln!(output, "Error: {}", error),
+            }
+        },
+        InputKind::Bytes => quote!{ crate::run(super::#task_function_name(input), output) },
+        InputKind::Reader => quote!{
+            let mut reader: &[u8] = input;
+            crate::run(super::#task_function_name(&mut reader), output)
+        },
+    };
     let task_internal_mod_name = input.sig.ident.to_string() + "_aoc_task_internal";
     let task_internal_mod_ident = Ident::new(&task_internal_mod_name, Span::call_site());
     quote!{
@@ -61,9 +169,15 @@ pub fn aoc_task(args: TokenStream, input: TokenStream) -> TokenStream{
                     #part
                 }
 
-                fn invoke(&self, read: &mut dyn std::io::BufRead){
-                    crate::run(super::#task_function_name(read));
+                fn variant(&self) -> Option<&'static str>{
+                    #variant
                 }
+
+                fn invoke(&self, input: &[u8], output: &mut dyn std::io::Write) -> std::io::Result<()>{
+                    #invoke
+                }
+
+                #phases
             }
 
             #[linkme::distributed_slice(crate::AOC_ENTRIES)]
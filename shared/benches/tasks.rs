@@ -0,0 +1,53 @@
+//! Criterion benchmarks of every registered task on its real input, one group per task. Parts that can be
+//! split into a parse and a solve phase get a benchmark of each phase next to the one of the whole task.
+//! Inputs are read from the inputs directory of the workspace, or from the directory in AOC_INPUTS, and
+//! tasks without an input are skipped. Reports are written to target/criterion.
+
+use std::{hint::black_box, path::PathBuf};
+
+use aoc_2023_shared::{differential::is_reference, task_name, AocTask, AOC_ENTRIES};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+fn input_directory() -> PathBuf{
+    std::env::var_os("AOC_INPUTS").map_or_else(|| PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/../inputs")), PathBuf::from)
+}
+
+fn tasks(c: &mut Criterion){
+    let mut entries: Vec<&dyn AocTask> = AOC_ENTRIES
+        .iter()
+        .map(|entry| *entry as &dyn AocTask)
+        .filter(|entry| !is_reference(*entry))
+        .collect();
+    entries.sort_by_key(|entry| (entry.year(), entry.day(), entry.part(), entry.variant()));
+
+    let directory = input_directory();
+    for entry in entries{
+        let name = task_name(entry);
+        let path = directory.join(format!("{}/{}.txt", entry.year(), entry.day()));
+        let Ok(input) = std::fs::read(&path) else{
+            eprintln!("Skipping {}, there is no input at {}", name, path.display());
+            continue;
+        };
+
+        let mut group = c.benchmark_group(&name);
+        let mut output = Vec::new();
+        group.bench_function("task", |b| b.iter(||{
+            output.clear();
+            entry.invoke(black_box(&input), &mut output)
+        }));
+
+        if let Some(parse) = entry.phases(){
+            group.bench_function("parse", |b| b.iter(|| parse(black_box(&input)).is_some()));
+            match parse(&input){
+                Some(mut solve) => {
+                    group.bench_function("solve", |b| b.iter(|| solve(&mut std::io::sink())));
+                },
+                None => eprintln!("Skipping the phases of {}, its input does not parse", name),
+            }
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, tasks);
+criterion_main!(benches);
@@ -0,0 +1,24 @@
+//! Records the commit the crate is built from, so that the runs in the history are filed under the code
+//! that actually produced them rather than whatever is checked out when the runner runs.
+
+use std::process::Command;
+
+fn git(args: &[&str]) -> Option<String>{
+    let output = Command::new("git").args(args).output().ok()?;
+    let text = String::from_utf8(output.stdout).ok()?;
+    output.status.success().then(|| text.trim().to_string())
+}
+
+fn main(){
+    // Any change to the sources or a commit can change the description
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=Cargo.toml");
+    if let Some(git_directory) = git(&["rev-parse", "--absolute-git-dir"]){
+        println!("cargo:rerun-if-changed={}/HEAD", git_directory);
+        println!("cargo:rerun-if-changed={}/index", git_directory);
+    }
+
+    if let Some(commit) = git(&["describe", "--always", "--dirty"]){
+        println!("cargo:rustc-env=AOC_COMMIT={}", commit);
+    }
+}
@@ -0,0 +1,139 @@
+//! Allocation accounting for tasks. The binary's global allocator counts every allocation made on a thread
+//! while that thread measures something, and otherwise only passes through to the system allocator.
+//! Counters are per thread, so tasks measured on other threads (like parallel tests) do not mix.
+
+use std::{alloc::{GlobalAlloc, Layout, System}, cell::Cell, sync::atomic::{AtomicBool, Ordering}};
+
+use serde::{Deserialize, Serialize};
+
+/// What a measured piece of code allocated
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AllocStats{
+    /// Number of allocations, growing or shrinking an allocation counts as another one
+    pub allocations: u64,
+    /// Bytes asked for over all allocations
+    pub bytes_allocated: u64,
+    /// Largest number of bytes allocated during the measurement and not yet freed
+    pub peak_bytes: u64,
+}
+
+impl std::fmt::Display for AllocStats{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        write!(f, "Allocated {} bytes in {} allocations, at most {} bytes at once", self.bytes_allocated, self.allocations, self.peak_bytes)
+    }
+}
+
+/// Set by the first counted allocation, which only happens when CountingAllocator is the global allocator
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+thread_local!{
+    static MEASURING: Cell<bool> = const { Cell::new(false) };
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+    static BYTES_ALLOCATED: Cell<u64> = const { Cell::new(0) };
+    /// Bytes allocated minus bytes freed since the measurement started, negative after freeing older memory
+    static LIVE_BYTES: Cell<i64> = const { Cell::new(0) };
+    static PEAK_BYTES: Cell<i64> = const { Cell::new(0) };
+}
+
+/// Counts for the current thread while it measures. Thread locals can be gone while a thread shuts down,
+/// allocations from then on are not counted.
+fn record(allocated: usize, freed: usize){
+    if !MEASURING.try_with(Cell::get).unwrap_or(false){
+        return;
+    }
+
+    INSTALLED.store(true, Ordering::Relaxed);
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + (allocated > 0) as u64));
+    let _ = BYTES_ALLOCATED.try_with(|bytes| bytes.set(bytes.get() + allocated as u64));
+    let _ = LIVE_BYTES.try_with(|live|{
+        live.set(live.get() + allocated as i64 - freed as i64);
+        let _ = PEAK_BYTES.try_with(|peak| peak.set(peak.get().max(live.get())));
+    });
+}
+
+/// The system allocator, counting for threads that measure
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator{
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8{
+        record(layout.size(), 0);
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8{
+        record(layout.size(), 0);
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout){
+        record(0, layout.size());
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8{
+        record(new_size, layout.size());
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Runs f and returns what it allocated on this thread. Only a program that installed CountingAllocator as
+/// its #[global_allocator] counts anything, the stats are None in any other.
+pub fn measure<T>(f: impl FnOnce() -> T) -> (T, Option<AllocStats>){
+    MEASURING.set(true);
+    // Until something was counted, an allocation of our own shows whether the counting allocator is installed
+    if !INSTALLED.load(Ordering::Relaxed){
+        drop(std::hint::black_box(Box::new(0u8)));
+    }
+
+    ALLOCATIONS.set(0);
+    BYTES_ALLOCATED.set(0);
+    LIVE_BYTES.set(0);
+    PEAK_BYTES.set(0);
+    let result = f();
+    MEASURING.set(false);
+
+    let stats = INSTALLED.load(Ordering::Relaxed).then(|| AllocStats{
+        allocations: ALLOCATIONS.get(),
+        bytes_allocated: BYTES_ALLOCATED.get(),
+        peak_bytes: PEAK_BYTES.get().max(0) as u64,
+    });
+    (result, stats)
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn test_measure(){
+        let (length, stats) = measure(||{
+            let first = std::hint::black_box(Vec::<u8>::with_capacity(1000));
+            drop(first);
+            let second = std::hint::black_box(vec![0u64; 100]);
+            second.len()
+        });
+        assert_eq!(length, 100);
+        assert_eq!(stats, Some(AllocStats{ allocations: 2, bytes_allocated: 1800, peak_bytes: 1000 }));
+    }
+
+    #[test]
+    fn test_measure_growth(){
+        let ((), stats) = measure(||{
+            let mut values = vec![0u8; 16];
+            values.reserve_exact(48);
+            std::hint::black_box(&values);
+        });
+        // Growing reallocates to 64 bytes, after which only those are live
+        assert_eq!(stats, Some(AllocStats{ allocations: 2, bytes_allocated: 80, peak_bytes: 64 }));
+    }
+
+    #[test]
+    fn test_nothing_outside_measurement(){
+        let ((), before) = measure(|| ());
+        let values = Box::new([1u8; 10]);
+        let ((), after) = measure(|| ());
+        assert_eq!(values.len(), 10);
+        assert_eq!(before, Some(AllocStats::default()));
+        assert_eq!(after, Some(AllocStats::default()));
+    }
+}
@@ -0,0 +1,202 @@
+//! Repeated timing of the tasks on their real inputs. Every input is read once and every run is handed the
+//! same buffer, so the times cover parsing and solving but not the file system. Results can be saved as a
+//! named baseline that later runs are compared against.
+
+use std::{path::PathBuf, time::{Duration, Instant}};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{alloc::{self, AllocStats}, differential::is_reference, task_name, AocTask, AOC_ENTRIES};
+
+/// Where baselines are kept, one JSON file per name
+const BASELINE_DIRECTORY: &str = "target/aoc-bench";
+
+#[derive(clap::Args)]
+pub struct BenchArgs{
+    /// Day to benchmark, every day when left out
+    #[arg(long)]
+    day: Option<u32>,
+
+    /// Timed runs of every task, after one untimed warm-up run
+    #[arg(long, default_value_t = 20)]
+    runs: u32,
+
+    /// Save the results as a baseline with this name
+    #[arg(long, value_name = "NAME")]
+    save_baseline: Option<String>,
+
+    /// Compare the results with the baseline saved under this name
+    #[arg(long, value_name = "NAME")]
+    baseline: Option<String>,
+
+    /// Count the allocations of the warm-up run
+    #[arg(long)]
+    alloc_stats: bool,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum BenchError{
+    #[error("there is no baseline named {0}")]
+    UnknownBaseline(String),
+    #[error("the baseline {0} is not readable: {1}")]
+    BadBaseline(String, serde_json::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Timing of a task over all of its runs
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Measurement{
+    pub task: String,
+    pub runs: u32,
+    pub min_ns: u64,
+    pub median_ns: u64,
+    pub max_ns: u64,
+    /// Allocations of the warm-up run, left out of baselines saved without them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allocations: Option<AllocStats>,
+}
+
+impl Measurement{
+    fn new(task: String, mut times: Vec<Duration>, allocations: Option<AllocStats>) -> Measurement{
+        times.sort_unstable();
+        let nanos = |time: Option<&Duration>| time.map_or(0, |time| time.as_nanos() as u64);
+        Measurement{
+            task,
+            runs: times.len() as u32,
+            min_ns: nanos(times.first()),
+            median_ns: nanos(times.get(times.len() / 2)),
+            max_ns: nanos(times.last()),
+            allocations,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Baseline{
+    measurements: Vec<Measurement>,
+}
+
+fn baseline_path(name: &str) -> PathBuf{
+    PathBuf::from(BASELINE_DIRECTORY).join(format!("{}.json", name))
+}
+
+fn load_baseline(name: &str) -> Result<Baseline, BenchError>{
+    let text = std::fs::read_to_string(baseline_path(name)).map_err(|_| BenchError::UnknownBaseline(name.into()))?;
+    serde_json::from_str(&text).map_err(|error| BenchError::BadBaseline(name.into(), error))
+}
+
+fn save_baseline(name: &str, baseline: &Baseline) -> Result<(), BenchError>{
+    std::fs::create_dir_all(BASELINE_DIRECTORY)?;
+    let text = serde_json::to_string_pretty(baseline).map_err(std::io::Error::from)?;
+    std::fs::write(baseline_path(name), text)?;
+    Ok(())
+}
+
+/// Runs a task once untimed and then the given number of times, discarding the answers. Allocations are
+/// counted during the untimed run, so counting does not slow down the timed ones.
+pub fn measure(task: &dyn AocTask, input: &[u8], runs: u32, alloc_stats: bool) -> std::io::Result<Measurement>{
+    let mut output = Vec::new();
+    let allocations = if alloc_stats{
+        let (result, stats) = alloc::measure(|| task.invoke(input, &mut output));
+        result?;
+        stats
+    }
+    else{
+        task.invoke(input, &mut output)?;
+        None
+    };
+
+    let mut times = Vec::with_capacity(runs as usize);
+    for _ in 0..runs{
+        output.clear();
+        let start = Instant::now();
+        task.invoke(input, &mut output)?;
+        times.push(start.elapsed());
+    }
+    Ok(Measurement::new(task_name(task), times, allocations))
+}
+
+fn micros(nanos: u64) -> f64{
+    nanos as f64 / 1000.0
+}
+
+pub fn bench(args: &BenchArgs) -> Result<(), BenchError>{
+    let baseline = args.baseline.as_deref().map(load_baseline).transpose()?;
+
+    let mut entries: Vec<&dyn AocTask> = AOC_ENTRIES
+        .iter()
+        .map(|entry| *entry as &dyn AocTask)
+        .filter(|entry| !is_reference(*entry) && args.day.is_none_or(|day| entry.day() == day))
+        .collect();
+    entries.sort_by_key(|entry| (entry.year(), entry.day(), entry.part(), entry.variant()));
+
+    let mut measurements = Vec::new();
+    for entry in entries{
+        let name = task_name(entry);
+        let Ok(input) = std::fs::read(format!("inputs/{}/{}.txt", entry.year(), entry.day())) else{
+            println!("{:<20} skipped, there is no input", name);
+            continue;
+        };
+
+        let measurement = measure(entry, &input, args.runs.max(1), args.alloc_stats)?;
+        print!("{:<20} median {:>10.1}mcs  min {:>10.1}mcs  max {:>10.1}mcs", name, micros(measurement.median_ns), micros(measurement.min_ns), micros(measurement.max_ns));
+        let previous = baseline.as_ref().and_then(|baseline| baseline.measurements.iter().find(|previous| previous.task == name));
+        if let Some(previous) = previous.filter(|previous| previous.median_ns > 0){
+            let change = (measurement.median_ns as f64 / previous.median_ns as f64 - 1.0) * 100.0;
+            print!("  {:+.1}% against {:.1}mcs", change, micros(previous.median_ns));
+        }
+        println!();
+        if let Some(allocations) = measurement.allocations{
+            print!("{:<20} {}", "", allocations);
+            if let Some(before) = previous.and_then(|previous| previous.allocations){
+                print!(" against {} bytes in {} allocations, at most {} bytes", before.bytes_allocated, before.allocations, before.peak_bytes);
+            }
+            println!();
+        }
+        measurements.push(measurement);
+    }
+
+    if let Some(name) = &args.save_baseline{
+        save_baseline(name, &Baseline{ measurements })?;
+        println!("Saved the baseline {}", name);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn test_measurement(){
+        let times = [5, 1, 3, 2, 4].map(Duration::from_nanos).to_vec();
+        let measurement = Measurement::new("2023-1-1".into(), times, None);
+        assert_eq!(measurement, Measurement{ task: "2023-1-1".into(), runs: 5, min_ns: 1, median_ns: 3, max_ns: 5, allocations: None });
+
+        let allocations = AllocStats{ allocations: 3, bytes_allocated: 100, peak_bytes: 60 };
+        let counted = Measurement{ allocations: Some(allocations), ..measurement.clone() };
+        let baseline = Baseline{ measurements: vec![measurement, counted] };
+        let parsed: Baseline = serde_json::from_str(&serde_json::to_string(&baseline).unwrap()).unwrap();
+        assert_eq!(parsed.measurements, baseline.measurements);
+    }
+
+    #[test]
+    fn test_baseline_without_allocations(){
+        let text = r#"{"measurements":[{"task":"2023-1-1","runs":1,"min_ns":1,"median_ns":1,"max_ns":1}]}"#;
+        let parsed: Baseline = serde_json::from_str(text).unwrap();
+        assert_eq!(parsed.measurements[0].allocations, None);
+        assert!(!serde_json::to_string(&parsed).unwrap().contains("allocations"));
+    }
+
+    #[test]
+    fn test_measure_task(){
+        let task = AOC_ENTRIES.iter().find(|entry| entry.day() == 6 && entry.part() == 1 && entry.variant().is_none()).unwrap();
+        let measurement = measure(*task, b"Time: 7 15 30\nDistance: 9 40 200\n", 3, true).unwrap();
+        assert_eq!(measurement.task, "2023-6-1");
+        assert_eq!(measurement.runs, 3);
+        assert!(measurement.min_ns <= measurement.median_ns && measurement.median_ns <= measurement.max_ns);
+        let allocations = measurement.allocations.unwrap();
+        assert!(allocations.allocations > 0 && allocations.peak_bytes <= allocations.bytes_allocated);
+    }
+}
@@ -1,17 +1,17 @@
 use thiserror::Error;
-use std::io::{BufRead};
+use std::collections::VecDeque;
+
+use crate::generate::Rng;
 
 
 #[derive(Error, Debug)]
-enum ParseLineError{
+pub enum ParseLineError{
     #[error("No digits were found")]
     NotEnoughDigits
 }
 
 #[derive(Error, Debug)]
-enum Error{
-    #[error("failed to open input file ({inner:?}")]
-    FailedToOpenInputFile{inner: std::io::Error},
+pub enum Error{
     #[error("failed to parse line {line}: {inner}")]
     FailedToParseLine{line: usize, inner: ParseLineError}
 }
@@ -28,56 +28,227 @@ const NUMBERS : [&str; 9] = [
     "nine",
 ];
 
-fn parse_line(value: &str) -> Result<u32, ParseLineError>{
-    // Find all symbols in the line
-    let symbols = value.char_indices().filter_map(|(index, char)|{
-        if char.is_ascii_digit(){
-            return Some(char.to_digit(10).unwrap());
+/// Words that stand for digits in a calibration line
+#[derive(Debug, Clone, Default)]
+pub struct Vocabulary{
+    words: Vec<(String, u32)>,
+}
+
+impl Vocabulary{
+    /// The digits 0 to 9 themselves, as used by part 1
+    pub fn digits() -> Vocabulary{
+        Vocabulary::default().with_digits()
+    }
+
+    /// Digits and the english words for one to nine, as used by part 2
+    pub fn english() -> Vocabulary{
+        NUMBERS
+            .iter()
+            .enumerate()
+            .fold(Vocabulary::digits(), |vocabulary, (index, word)| vocabulary.with_word(word, index as u32 + 1))
+    }
+
+    fn with_digits(self) -> Vocabulary{
+        (0..10).fold(self, |vocabulary, digit| vocabulary.with_word(&digit.to_string(), digit))
+    }
+
+    /// Adds a word for a digit, empty words are ignored
+    pub fn with_word(mut self, word: &str, digit: u32) -> Vocabulary{
+        if !word.is_empty(){
+            self.words.push((word.into(), digit));
         }
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Match{
+    start: usize,
+    length: usize,
+    digit: u32,
+}
 
-        let remaining_value= &value[index..];
-        for (index, word) in NUMBERS.iter().enumerate(){
-            if remaining_value.starts_with(word){
-                return Some(index as u32 + 1);
+/// Aho-Corasick automaton finding every, possibly overlapping, vocabulary word in a single pass
+struct Matcher{
+    /// Next state for every state and byte, with the failure links already followed
+    transitions: Vec<[u32; 256]>,
+    /// Length and digit of every word that ends in each state
+    outputs: Vec<Vec<(usize, u32)>>,
+}
+
+impl Matcher{
+    fn new(vocabulary: &Vocabulary) -> Matcher{
+        // Build the trie, 0 marks a missing edge since the root is never a child
+        let mut transitions = vec![[0u32; 256]];
+        let mut outputs = vec![Vec::new()];
+        for (word, digit) in vocabulary.words.iter(){
+            let mut state = 0;
+            for byte in word.bytes(){
+                if transitions[state][byte as usize] == 0{
+                    transitions[state][byte as usize] = transitions.len() as u32;
+                    transitions.push([0; 256]);
+                    outputs.push(Vec::new());
+                }
+                state = transitions[state][byte as usize] as usize;
+            }
+            outputs[state].push((word.len(), *digit));
+        }
+
+        // Breadth first, so the failure state of every node is finished before its children
+        let mut failure = vec![0usize; transitions.len()];
+        let mut queue: VecDeque<usize> = transitions[0].iter().filter(|next| **next != 0).map(|next| *next as usize).collect();
+        while let Some(state) = queue.pop_front(){
+            let inherited = outputs[failure[state]].clone();
+            outputs[state].extend(inherited);
+
+            let fallbacks = transitions[failure[state]];
+            for (next, fallback) in transitions[state].iter_mut().zip(fallbacks){
+                if *next == 0{
+                    *next = fallback;
+                } else{
+                    failure[*next as usize] = fallback as usize;
+                    queue.push_back(*next as usize);
+                }
             }
         }
 
-        None
+        Matcher{ transitions, outputs }
+    }
+
+    fn find_all<'a>(&'a self, value: &'a [u8]) -> impl Iterator<Item = Match> + 'a{
+        value.iter().enumerate().scan(0usize, move |state, (index, byte)|{
+            *state = self.transitions[*state][*byte as usize] as usize;
+            Some(self.outputs[*state].iter().map(move |(length, digit)| Match{ start: index + 1 - length, length: *length, digit: *digit }))
+        })
+        .flatten()
+    }
+}
+
+fn parse_line(value: &[u8], matcher: &Matcher) -> Result<u32, ParseLineError>{
+    // Earliest starting word first, and the longest of those that start at the same place.
+    // Last is the latest starting word, and the longest of those, kept in the same pass.
+    let (first, last) = matcher.find_all(value).fold((None::<Match>, None::<Match>), |(first, last), found|{
+        let first = match first{
+            Some(first) if (first.start, std::cmp::Reverse(first.length)) <= (found.start, std::cmp::Reverse(found.length)) => Some(first),
+            _ => Some(found),
+        };
+        let last = match last{
+            Some(last) if (last.start, last.length) > (found.start, found.length) => Some(last),
+            _ => Some(found),
+        };
+        (first, last)
     });
 
-    let first = symbols.clone().next().ok_or(ParseLineError::NotEnoughDigits)?;
-    let last = symbols.clone().last().ok_or(ParseLineError::NotEnoughDigits)?;
-    Ok(first * 10 + last)
+    match (first, last){
+        (Some(first), Some(last)) => Ok(first.digit * 10 + last.digit),
+        _ => Err(ParseLineError::NotEnoughDigits),
+    }
 }
 
-fn parse_calibration_document<R: BufRead>(input: R)-> Result<u32, Error>{
-    let lines = input.lines().flatten();
+/// Sum of the calibration values of every line, reading digits with the given vocabulary
+pub fn parse_calibration_document(input: &[u8], vocabulary: &Vocabulary)-> Result<u32, Error>{
+    let matcher = Matcher::new(vocabulary);
 
-    Ok(lines
+    crate::input::lines(input)
         .enumerate()
-        .map(|(line, value)| parse_line(&value).map_err(|e| Error::FailedToParseLine {line, inner: e}))
-        .try_fold(0, |acc, value| Ok(acc + value?))?)
+        .map(|(index, value)| parse_line(value, &matcher).map_err(|e| Error::FailedToParseLine {line: index + 1, inner: e}))
+        .try_fold(0, |acc, value| Ok(acc + value?))
 }
 
 
 #[aoc_2023_markup::aoc_task(2023, 1, 1)]
-fn part1(reader: &mut dyn BufRead) -> Result<u32, Error>{
-    parse_calibration_document(reader)
+pub fn part1(input: &[u8]) -> Result<u32, Error>{
+    parse_calibration_document(input, &Vocabulary::digits())
+}
+
+#[aoc_2023_markup::aoc_task(2023, 1, 2)]
+pub fn part2(input: &[u8]) -> Result<u32, Error>{
+    parse_calibration_document(input, &Vocabulary::english())
+}
+
+/// Lines of letters mixed with digits and spelled numbers, every line holds at least one digit
+pub fn generate(rng: &mut Rng, size: usize) -> String{
+    let mut result = String::new();
+    for _ in 0..size{
+        let mut line = String::new();
+        let digit_at = rng.range(0..4);
+        for piece in 0..rng.range(4..10).max(digit_at + 1){
+            if piece == digit_at{
+                line.push(char::from(b'1' + rng.below(9) as u8));
+            } else if rng.chance(0.3){
+                line.push_str(rng.choose::<&str>(&NUMBERS));
+            } else{
+                line.push(char::from(b'a' + rng.below(26) as u8));
+            }
+        }
+        result.push_str(&line);
+        result.push('\n');
+    }
+    result
+}
+
+/// Reads the document with both vocabularies
+pub fn fuzz(data: &[u8]){
+    let _ = parse_calibration_document(data, &Vocabulary::digits());
+    let _ = parse_calibration_document(data, &Vocabulary::english());
 }
 
 #[cfg(test)]
 mod tests{
+    use proptest::prelude::*;
+    use crate::generate::Rng;
     use super::*;
 
     #[test]
     fn test_single_line(){
-        const SAMPLE_INPUT : &str= "pqr3stu8vwx";
-        assert_eq!(parse_line(SAMPLE_INPUT).unwrap(), 38);
+        const SAMPLE_INPUT : &[u8]= b"pqr3stu8vwx";
+        assert_eq!(parse_line(SAMPLE_INPUT, &Matcher::new(&Vocabulary::digits())).unwrap(), 38);
     }
 
     #[test]
     fn test_single_line_spelled(){
-        assert_eq!(parse_line("two1nine").unwrap(), 29);
+        let matcher = Matcher::new(&Vocabulary::english());
+        assert_eq!(parse_line(b"two1nine", &matcher).unwrap(), 29);
+        assert_eq!(parse_line(b"eightwo", &matcher).unwrap(), 82);
+        assert_eq!(parse_line(b"oneight", &matcher).unwrap(), 18);
+        assert_eq!(parse_line(b"xtwone3four", &matcher).unwrap(), 24);
+        assert_eq!(parse_line(b"seven", &matcher).unwrap(), 77);
+    }
+
+    #[test]
+    fn test_digits_only(){
+        let matcher = Matcher::new(&Vocabulary::digits());
+        assert_eq!(parse_line(b"two1nine", &matcher).unwrap(), 11);
+        assert!(parse_line(b"eightwothree", &matcher).is_err());
+    }
+
+    #[test]
+    fn test_custom_vocabulary(){
+        let german = ["eins", "zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht", "neun"]
+            .iter()
+            .enumerate()
+            .fold(Vocabulary::digits().with_word("null", 0), |vocabulary, (index, word)| vocabulary.with_word(word, index as u32 + 1));
+        let matcher = Matcher::new(&german);
+        assert_eq!(parse_line("xnullfünfx".as_bytes(), &matcher).unwrap(), 5);
+        assert_eq!(parse_line(b"siebeneins", &matcher).unwrap(), 71);
+        assert_eq!(parse_line(b"two1nine", &matcher).unwrap(), 11);
+
+        let matcher = Matcher::new(&Vocabulary::english().with_word("zero", 0));
+        assert_eq!(parse_line(b"zeroneightwo", &matcher).unwrap(), 2);
+        assert_eq!(parse_calibration_document(b"zeroneightwo\nzero", &Vocabulary::english().with_word("zero", 0)).unwrap(), 2);
+
+        // Words sharing a start prefer the longer one at the front and the later start at the back
+        let matcher = Matcher::new(&Vocabulary::default().with_word("se", 1).with_word("seven", 7).with_word("even", 8));
+        assert_eq!(parse_line(b"seven", &matcher).unwrap(), 78);
+    }
+
+    #[test]
+    fn test_generated(){
+        let input = generate(&mut Rng::new(1), 200);
+        let digits = parse_calibration_document(input.as_bytes(), &Vocabulary::digits()).unwrap();
+        let english = parse_calibration_document(input.as_bytes(), &Vocabulary::english()).unwrap();
+        assert!(digits >= 200 * 11);
+        assert!(english >= 200 * 11);
     }
 
     #[test]
@@ -87,7 +258,7 @@ mod tests{
                                      a1b2c3d4e5f
                                      treb7uchet".as_bytes();
 
-        let output = parse_calibration_document(SAMPLE_INPUT).unwrap();
+        let output = parse_calibration_document(SAMPLE_INPUT, &Vocabulary::digits()).unwrap();
         assert_eq!(output, 142);
     }
 
@@ -100,7 +271,17 @@ mod tests{
                                       4nineeightseven2
                                       zoneight234
                                       7pqrstsixteen".as_bytes();
-        let output = parse_calibration_document(SAMPLE_INPUT).unwrap();
+        let output = parse_calibration_document(SAMPLE_INPUT, &Vocabulary::english()).unwrap();
         assert_eq!(output, 281);
     }
+
+    proptest!{
+        #[test]
+        fn test_parse_line_digits(line in "[a-z0-9]{0,20}"){
+            let digits: Vec<u32> = line.chars().filter_map(|char| char.to_digit(10)).collect();
+            let parsed = parse_line(line.as_bytes(), &Matcher::new(&Vocabulary::digits())).ok();
+            prop_assert_eq!(parsed, digits.first().zip(digits.last()).map(|(first, last)| first * 10 + last));
+        }
+    }
+
 }
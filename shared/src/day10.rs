@@ -1,5 +1,9 @@
 use std::collections::{BinaryHeap, HashSet};
-use itertools::Itertools;
+use thiserror::Error;
+
+use crate::generate::Rng;
+
+pub mod render;
 
 #[derive(PartialEq, Eq, Debug, Copy, Clone)]
 #[repr(u8)]
@@ -14,41 +18,40 @@ enum PipeType {
     None,
 }
 
+/// The two directions every pipe connects, the single source of truth for pipe shapes
+const PIPE_CONNECTIONS: [(PipeType, [Direction; 2]); 6] = [
+    (PipeType::NS, [Direction::Up, Direction::Down]),
+    (PipeType::EW, [Direction::Left, Direction::Right]),
+    (PipeType::NE, [Direction::Up, Direction::Right]),
+    (PipeType::NW, [Direction::Up, Direction::Left]),
+    (PipeType::SW, [Direction::Down, Direction::Left]),
+    (PipeType::SE, [Direction::Down, Direction::Right]),
+];
+
 impl PipeType{
+    /// The directions this pipe connects, None for the start and ground tiles
+    fn connections(self) -> Option<[Direction; 2]> {
+        PIPE_CONNECTIONS
+            .iter()
+            .find(|(pipe, _)| *pipe == self)
+            .map(|(_, directions)| *directions)
+    }
+
     /// Checks whether the given pipe type can potentially support a connection to a pipe in the given direction
     fn can_connect_to_direction(self, direction: Direction) -> bool {
         match self{
-            PipeType::NS => direction == Direction::Up || direction == Direction::Down,
-            PipeType::EW => direction == Direction::Left || direction == Direction::Right,
-            PipeType::NE => direction == Direction::Up || direction == Direction::Right,
-            PipeType::NW => direction == Direction::Up || direction == Direction::Left,
-            PipeType::SW => direction == Direction::Down || direction == Direction::Left,
-            PipeType::SE => direction == Direction::Down || direction == Direction::Right,
             PipeType::Start => true,
             PipeType::None => false,
+            pipe => pipe.connections().is_some_and(|directions| directions.contains(&direction)),
         }
     }
 
-    /// Get a pipe that connects between two unqiue directions in a given tile
-    fn from_directions(a: Direction, b: Direction) -> PipeType {
-        assert_ne!(a, b);
-
-        use Direction::*;
-        match (a, b) {
-            (Up, Right) => PipeType::NE,
-            (Up, Down) => PipeType::NS,
-            (Up, Left) => PipeType::NW,
-            (Right, Up) => PipeType::NE,
-            (Right, Down) => PipeType::SE,
-            (Right, Left) => PipeType::EW,
-            (Down, Up) => PipeType::NS,
-            (Down, Right) => PipeType::SE,
-            (Down, Left) => PipeType::SW,
-            (Left, Up) => PipeType::NS,
-            (Left, Right) => PipeType::SE,
-            (Left, Down) => PipeType::SW,
-            _ => unreachable!(),
-        }
+    /// Get the pipe that connects two unique directions in a given tile, in either order
+    fn from_directions(a: Direction, b: Direction) -> Option<PipeType> {
+        PIPE_CONNECTIONS
+            .iter()
+            .find(|(_, directions)| *directions == [a, b] || *directions == [b, a])
+            .map(|(pipe, _)| *pipe)
     }
 }
 
@@ -70,6 +73,33 @@ impl TryFrom<char> for PipeType{
     }
 }
 
+impl From<PipeType> for char {
+    fn from(value: PipeType) -> Self {
+        match value {
+            PipeType::Start => 'S',
+            PipeType::None => '.',
+            PipeType::NS => '|',
+            PipeType::EW => '-',
+            PipeType::NW => 'J',
+            PipeType::SW => '7',
+            PipeType::SE => 'F',
+            PipeType::NE => 'L',
+        }
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error{
+    #[error("failed to parse the map")]
+    Parse,
+    #[error("the map has no start tile")]
+    NoStart,
+    #[error("the start tile at {location:?} connects to {count} pipes, expected exactly 2")]
+    StartConnections{ location: (usize, usize), count: usize },
+    #[error("the loop is broken at {location:?}")]
+    BrokenLoop{ location: (usize, usize) },
+}
+
 #[derive(PartialEq, Eq, Debug, Copy, Clone)]
 enum Direction {
     Up,
@@ -173,10 +203,20 @@ impl<'a> Iterator for ConnectionIterator<'a> {
 }
 
 #[derive(PartialEq, Eq, Debug, Clone)]
-struct Map {
+pub struct Map {
     data: Vec<Vec<PipeType>>,
 }
 
+impl std::fmt::Display for Map {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in self.data.iter() {
+            let line: String = row.iter().map(|pipe| char::from(*pipe)).collect();
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
 impl Map{
     /// Iterate between the connecting directions for a pipe in a given location
     fn find_connecting_directions(
@@ -202,9 +242,8 @@ impl Map{
         None
     }
 
-    fn get_pipes_in_loop(&self) -> HashSet<(usize, usize)>{
+    fn get_pipes_in_loop(&self, start: (usize, usize)) -> HashSet<(usize, usize)>{
         let mut visited = HashSet::new();
-        let start = self.find_start().unwrap();
         let mut queue = Vec::new();
         queue.push(start);
         while let Some(item) = queue.pop() {
@@ -219,42 +258,91 @@ impl Map{
         visited
     }
 
+    /// Infers the pipe hidden under the start tile from the neighbours connecting to it
+    fn infer_start(&self) -> Result<((usize, usize), PipeType), Error> {
+        let start = self.find_start().ok_or(Error::NoStart)?;
+        let directions: Vec<Direction> = self.find_connecting_directions(start).collect();
+        match directions[..] {
+            [a, b] => Ok((start, PipeType::from_directions(a, b).unwrap())),
+            _ => Err(Error::StartConnections {
+                location: start,
+                count: directions.len(),
+            }),
+        }
+    }
+
+    /// Walks the loop once from the start, returning every tile on it in order
+    fn trace_loop(&self) -> Result<Vec<(usize, usize)>, Error> {
+        let (start, start_pipe) = self.infer_start()?;
+        let [mut direction, _] = start_pipe.connections().unwrap();
+
+        let mut vertices = vec![start];
+        let mut location = start;
+        loop {
+            let broken = Error::BrokenLoop { location };
+            location = self.neighbour(location, direction).ok_or(broken)?;
+            if location == start {
+                return Ok(vertices);
+            }
+            vertices.push(location);
+
+            // Leave the pipe through the side we did not enter from
+            let entered_from = direction.opposite();
+            direction = match self.data[location.1][location.0].connections() {
+                Some([a, b]) if a == entered_from => b,
+                Some([a, b]) if b == entered_from => a,
+                _ => return Err(Error::BrokenLoop { location }),
+            };
+        }
+    }
+
+    /// Location of the adjacent tile in a direction, None when it lies outside the map
+    fn neighbour(&self, location: (usize, usize), direction: Direction) -> Option<(usize, usize)> {
+        let (x, y) = match direction {
+            Direction::Up => (location.0, location.1.checked_sub(1)?),
+            Direction::Left => (location.0.checked_sub(1)?, location.1),
+            _ => direction.move_location(location),
+        };
+        self.data.get(y)?.get(x)?;
+        Some((x, y))
+    }
+
     /// Gets a map with only pipes that are part of the loop.
     /// Pipes not in the loop are replaced with ground
     /// The start pipe is replaced with a it's infered pipe type
-    fn get_loop_map(&self) -> Map{
+    fn get_loop_map(&self) -> Result<Map, Error>{
+        let (start, start_pipe) = self.infer_start()?;
+
         // Figure out which pipes are part of the loop containing the start
-        let pipes_in_loop = self.get_pipes_in_loop();
+        let pipes_in_loop = self.get_pipes_in_loop(start);
 
         // Replace pipes that are not in the loops with ground
         let mut map = self.clone();
         for (y, row) in map.data.iter_mut().enumerate() {
             for (x, value) in row.iter_mut().enumerate() {
-                if *value != PipeType::Start && *value != PipeType::None {
-                    if !pipes_in_loop.contains(&(x, y)) {
-                        *value = PipeType::None;
-                    }
+                if *value != PipeType::Start
+                    && *value != PipeType::None
+                    && !pipes_in_loop.contains(&(x, y))
+                {
+                    *value = PipeType::None;
                 }
             }
         }
 
         // Replace start with a matching pipe type
-        let start = self.find_start().unwrap();
-        let (a, b) = map.find_connecting_directions(start).collect_tuple().unwrap();
-        map.data[start.1][start.0] = PipeType::from_directions(a, b);
+        map.data[start.1][start.0] = start_pipe;
 
-        map
+        Ok(map)
     }
 }
 
-fn parse_line(line: &str) -> Option<Vec<PipeType>> {
-    line.chars().map(|item| item.try_into().ok()).collect()
+fn parse_line(line: &[u8]) -> Option<Vec<PipeType>> {
+    line.iter().map(|item| char::from(*item).try_into().ok()).collect()
 }
 
-fn parse_map<R: std::io::BufRead>(input: R) -> Option<Map> {
-    let lines: Vec<Vec<PipeType>> = input
-        .lines()
-        .map(|line| line.ok().and_then(|line| parse_line(&line)))
+pub fn parse_map(input: &[u8]) -> Option<Map> {
+    let lines: Vec<Vec<PipeType>> = crate::input::lines(input)
+        .map(parse_line)
         .collect::<Option<_>>()?;
     Some(Map { data: lines })
 }
@@ -273,7 +361,7 @@ impl Ord for QueuedPath {
 
 impl PartialOrd for QueuedPath {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(&other))
+        Some(self.cmp(other))
     }
 }
 
@@ -283,8 +371,8 @@ impl PartialEq for QueuedPath {
     }
 }
 
-fn find_furthest_pipe_from_start(map: &Map) -> usize {
-    let start = map.find_start().unwrap();
+pub fn find_furthest_pipe_from_start(map: &Map) -> Result<usize, Error> {
+    let (start, _) = map.infer_start()?;
     let mut visited = HashSet::new();
     let mut queue = BinaryHeap::new();
     let mut furthest_visited = 0;
@@ -310,20 +398,20 @@ fn find_furthest_pipe_from_start(map: &Map) -> usize {
         }
     }
 
-    furthest_visited
+    Ok(furthest_visited)
 }
 
-fn count_inside_loop(map: &Map) -> usize {
-    let map = map.get_loop_map();
+/// Marks every tile enclosed by the loop, scanning each row and counting loop crossings
+fn find_inside_loop(map: &Map) -> Result<Vec<Vec<bool>>, Error> {
+    let map = map.get_loop_map()?;
 
-    let mut count = 0;
-    for y in 0..map.data.len() {
+    let mut inside = Vec::with_capacity(map.data.len());
+    for line in map.data.iter() {
         let mut entry = None;
         let mut hits = 0;
-        let line = &map.data[y];
+        let mut inside_line = vec![false; line.len()];
 
-        for x in 0..line.len() {
-            let pipe = line[x];
+        for (x, &pipe) in line.iter().enumerate() {
             match pipe {
                 // | pipes are always hits
                 PipeType::NS => hits += 1,
@@ -332,34 +420,230 @@ fn count_inside_loop(map: &Map) -> usize {
                 PipeType::NE | PipeType::SE => entry = Some(pipe),
 
                 // We count L turns as hits when the previous L turn we hit on this line was in the opposite direction
-                PipeType::NW if entry.unwrap() == PipeType::SE => hits += 1,
-                PipeType::SW if entry.unwrap() == PipeType::NE => hits += 1,
+                PipeType::NW if entry == Some(PipeType::SE) => hits += 1,
+                PipeType::SW if entry == Some(PipeType::NE) => hits += 1,
 
-                PipeType::None if hits % 2 != 0 => count += 1,
+                PipeType::None if hits % 2 != 0 => inside_line[x] = true,
                 _ => (),
             }
         }
+
+        inside.push(inside_line);
     }
 
-    count
+    Ok(inside)
 }
 
-#[aoc_2023_markup::aoc_task(2023, 10, 1)]
-fn part1<R: std::io::BufRead>(input: R) -> Option<usize> {
-    let map = parse_map(input)?;
-    Some(find_furthest_pipe_from_start(&map))
+pub fn count_inside_loop(map: &Map) -> Result<usize, Error> {
+    let inside = find_inside_loop(map)?;
+    Ok(inside.iter().flatten().filter(|inside| **inside).count())
 }
 
-#[aoc_2023_markup::aoc_task(2023, 10, 2)]
-fn part2<R: std::io::BufRead>(input: R) -> Option<usize> {
-    let mut map = parse_map(input)?;
-    Some(count_inside_loop(&mut map))
+/// Counts the tiles enclosed by the loop from its area.
+/// The shoelace formula gives the area enclosed by the loop tile centers, Pick's theorem then relates it to whole tiles:
+/// area = inside + boundary / 2 - 1
+pub fn count_inside_loop_shoelace(map: &Map) -> Result<usize, Error> {
+    let vertices = map.trace_loop()?;
+
+    let twice_area = vertices
+        .iter()
+        .zip(vertices.iter().cycle().skip(1))
+        .map(|(a, b)| a.0 as i64 * b.1 as i64 - b.0 as i64 * a.1 as i64)
+        .sum::<i64>()
+        .unsigned_abs() as usize;
+
+    Ok((twice_area + 2 - vertices.len()) / 2)
+}
+
+/// Counts the enclosed tiles by flooding everything outside the loop.
+/// The map is drawn at three times its resolution, which opens up the gaps between adjacent pipes for the flood.
+fn count_inside_loop_flood(map: &Map) -> Result<usize, Error> {
+    let map = map.get_loop_map()?;
+    let height = map.data.len();
+    let width = map.data.iter().map(|row| row.len()).max().unwrap_or(0);
+
+    // A margin of one cell keeps the outside connected all around the loop
+    let (rows, columns) = (3 * height + 2, 3 * width + 2);
+    let mut blocked = vec![vec![false; columns]; rows];
+    for (y, row) in map.data.iter().enumerate() {
+        for (x, pipe) in row.iter().enumerate() {
+            if let Some(directions) = pipe.connections() {
+                let center = (3 * x + 2, 3 * y + 2);
+                blocked[center.1][center.0] = true;
+                for direction in directions {
+                    let (x, y) = direction.move_location(center);
+                    blocked[y][x] = true;
+                }
+            }
+        }
+    }
+
+    let mut outside = vec![vec![false; columns]; rows];
+    let mut queue = vec![(0, 0)];
+    while let Some((x, y)) = queue.pop() {
+        if blocked[y][x] || outside[y][x] {
+            continue;
+        }
+        outside[y][x] = true;
+
+        if x > 0 {
+            queue.push((x - 1, y));
+        }
+        if y > 0 {
+            queue.push((x, y - 1));
+        }
+        if x + 1 < columns {
+            queue.push((x + 1, y));
+        }
+        if y + 1 < rows {
+            queue.push((x, y + 1));
+        }
+    }
+
+    // A tile is inside when the flood never reached its center and the loop does not pass through it
+    let inside = map
+        .data
+        .iter()
+        .enumerate()
+        .flat_map(|(y, row)| (0..row.len()).map(move |x| (3 * x + 2, 3 * y + 2)))
+        .filter(|(x, y)| !blocked[*y][*x] && !outside[*y][*x])
+        .count();
+    Ok(inside)
+}
+
+#[aoc_2023_markup::aoc_task(2023, 10, 1, parse = parse_map, solve = find_furthest_pipe_from_start)]
+pub fn part1(input: &[u8]) -> Result<usize, Error> {
+    let map = parse_map(input).ok_or(Error::Parse)?;
+    find_furthest_pipe_from_start(&map)
+}
+
+#[aoc_2023_markup::aoc_task(2023, 10, 2, parse = parse_map, solve = count_inside_loop)]
+pub fn part2(input: &[u8]) -> Result<usize, Error> {
+    let map = parse_map(input).ok_or(Error::Parse)?;
+    count_inside_loop(&map)
+}
+
+#[aoc_2023_markup::aoc_task(2023, 10, 2, "shoelace")]
+pub fn part2_shoelace(input: &[u8]) -> Result<usize, Error> {
+    let map = parse_map(input).ok_or(Error::Parse)?;
+    count_inside_loop_shoelace(&map)
+}
+
+#[aoc_2023_markup::aoc_task(2023, 10, 2, "reference")]
+fn part2_reference(input: &[u8]) -> Result<usize, Error> {
+    let map = parse_map(input).ok_or(Error::Parse)?;
+    count_inside_loop_flood(&map)
+}
+
+/// A generated map, together with the answers it was built for while testing
+struct GeneratedLoop {
+    input: String,
+    #[cfg(test)]
+    loop_length: usize,
+    #[cfg(test)]
+    inside: usize,
+}
+
+/// Cells of the tree shape are this many tiles wide, so that some tiles end up inside the loop
+const GENERATED_SCALE: usize = 2;
+
+/// Builds a closed loop around a random tree, on a map of about size by size tiles.
+/// Nodes of a random tree sit on every other cell and the tree edges fill the cells between them.
+/// The outline of this shape has no holes and never touches itself, so it is a single loop,
+/// and Pick's theorem gives the number of tiles inside it from the area of the shape.
+fn generate_loop(rng: &mut Rng, size: usize) -> GeneratedLoop {
+    let nodes = (size / (2 * GENERATED_SCALE)).max(2);
+    let cells = 2 * nodes - 1;
+
+    // Grow the tree from the top left corner by picking random edges out of it, leaving some room for junk
+    let mut shape = vec![vec![false; cells]; cells];
+    shape[0][0] = true;
+    let mut frontier = vec![((0, 0), (0, 1)), ((0, 0), (1, 0))];
+    let mut remaining = rng.range(nodes * nodes / 2..nodes * nodes);
+    while remaining > 0 && !frontier.is_empty() {
+        let index = rng.range(0..frontier.len());
+        let (from, to): ((usize, usize), (usize, usize)) = frontier.swap_remove(index);
+        if shape[2 * to.0][2 * to.1] {
+            continue;
+        }
+
+        shape[from.0 + to.0][from.1 + to.1] = true;
+        shape[2 * to.0][2 * to.1] = true;
+        remaining -= 1;
+        for (dy, dx) in [(0, 1), (1, 0), (0, -1), (-1, 0)] {
+            let next = (to.0 as isize + dy, to.1 as isize + dx);
+            if (0..nodes as isize).contains(&next.0) && (0..nodes as isize).contains(&next.1) {
+                frontier.push((to, (next.0 as usize, next.1 as usize)));
+            }
+        }
+    }
+
+    // Tiles sit on the corners of the cells, a tile is on the loop where the shape has an edge
+    let in_shape = |row: usize, column: usize| {
+        row > 0 && column > 0 && shape.get((row - 1) / GENERATED_SCALE).and_then(|line| line.get((column - 1) / GENERATED_SCALE)).copied().unwrap_or(false)
+    };
+    let tiles = cells * GENERATED_SCALE + 1;
+    let mut map = vec![vec![PipeType::None; tiles]; tiles];
+    let mut on_loop = Vec::new();
+    for (row, line) in map.iter_mut().enumerate() {
+        for (column, tile) in line.iter_mut().enumerate() {
+            let (row, column) = (row + 1, column + 1);
+            let edges = [
+                (Direction::Up, in_shape(row - 1, column - 1) != in_shape(row - 1, column)),
+                (Direction::Down, in_shape(row, column - 1) != in_shape(row, column)),
+                (Direction::Left, in_shape(row - 1, column - 1) != in_shape(row, column - 1)),
+                (Direction::Right, in_shape(row - 1, column) != in_shape(row, column)),
+            ];
+            let mut directions = edges.iter().filter(|(_, edge)| *edge).map(|(direction, _)| *direction);
+            if let (Some(a), Some(b)) = (directions.next(), directions.next()) {
+                *tile = PipeType::from_directions(a, b).unwrap();
+                on_loop.push((column - 1, row - 1));
+            }
+        }
+    }
+
+    // Junk everywhere else, except next to the start where it could look like a third connection
+    let start = *rng.choose(&on_loop);
+    let junk = [PipeType::NS, PipeType::EW, PipeType::NE, PipeType::NW, PipeType::SW, PipeType::SE];
+    for (y, line) in map.iter_mut().enumerate() {
+        for (x, tile) in line.iter_mut().enumerate() {
+            let near_start = x.abs_diff(start.0) + y.abs_diff(start.1) <= 1;
+            if *tile == PipeType::None && !near_start && rng.chance(0.5) {
+                *tile = *rng.choose(&junk);
+            }
+        }
+    }
+    map[start.1][start.0] = PipeType::Start;
+
+    GeneratedLoop {
+        input: Map { data: map }.to_string(),
+        #[cfg(test)]
+        loop_length: on_loop.len(),
+        #[cfg(test)]
+        inside: shape.iter().flatten().filter(|cell| **cell).count() * GENERATED_SCALE * GENERATED_SCALE + 1 - on_loop.len() / 2,
+    }
+}
+
+/// Map of about size by size tiles with a single loop winding around, surrounded by junk pipes
+pub fn generate(rng: &mut Rng, size: usize) -> String {
+    generate_loop(rng, size).input
+}
+
+pub fn fuzz(data: &[u8]) {
+    let _ = parse_map(data);
+}
+
+/// Rewrites the map with every row on its own line
+pub fn normalize(data: &[u8]) -> Option<String> {
+    parse_map(data).map(|map| map.to_string())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use indoc::indoc;
+    use proptest::prelude::*;
+    use crate::generate::Rng;
 
     const INPUT: &[u8] = indoc! {"
         .....
@@ -412,6 +696,84 @@ mod tests {
                 ]
             }
         );
+        assert_eq!(map.to_string().as_bytes(), INPUT);
+    }
+
+    const ALL_DIRECTIONS: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    #[test]
+    fn test_from_directions_exhaustive() {
+        for a in ALL_DIRECTIONS {
+            for b in ALL_DIRECTIONS {
+                let pipe = PipeType::from_directions(a, b);
+                assert_eq!(pipe, PipeType::from_directions(b, a));
+
+                if a == b {
+                    assert_eq!(pipe, None);
+                    continue;
+                }
+
+                // The pipe connects exactly the two requested directions
+                let pipe = pipe.unwrap();
+                for direction in ALL_DIRECTIONS {
+                    assert_eq!(
+                        pipe.can_connect_to_direction(direction),
+                        direction == a || direction == b,
+                        "{:?} from {:?} and {:?} towards {:?}",
+                        pipe,
+                        a,
+                        b,
+                        direction
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_infer_start_every_shape() {
+        const RING: [&str; 3] = ["F-7", "|.|", "L-J"];
+
+        // Hiding every pipe of the ring under the start tile covers all six shapes
+        for y in 0..3 {
+            for x in 0..3 {
+                if (x, y) == (1, 1) {
+                    continue;
+                }
+
+                let mut rows: Vec<Vec<char>> = RING.iter().map(|row| row.chars().collect()).collect();
+                let expected = PipeType::try_from(rows[y][x]).unwrap();
+                rows[y][x] = 'S';
+                let input: String = rows.iter().map(|row| row.iter().collect::<String>() + "\n").collect();
+
+                let map = parse_map(input.as_bytes()).unwrap();
+                assert_eq!(map.infer_start(), Ok(((x, y), expected)));
+                assert_eq!(map.get_loop_map().unwrap(), parse_map(RING.join("\n").as_bytes()).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_infer_start_invalid() {
+        let map = parse_map("...\n.S.\n...".as_bytes()).unwrap();
+        assert_eq!(
+            map.get_loop_map(),
+            Err(Error::StartConnections { location: (1, 1), count: 0 })
+        );
+
+        let map = parse_map(".|.\n-S-\n.|.".as_bytes()).unwrap();
+        assert_eq!(
+            map.get_loop_map(),
+            Err(Error::StartConnections { location: (1, 1), count: 4 })
+        );
+
+        let map = parse_map("F-7\n|.|\nL-J".as_bytes()).unwrap();
+        assert_eq!(map.get_loop_map(), Err(Error::NoStart));
     }
 
     #[test]
@@ -432,10 +794,32 @@ mod tests {
     #[test]
     fn test_furthest_visited() {
         let map = parse_map(INPUT).unwrap();
-        assert_eq!(find_furthest_pipe_from_start(&map), 4);
+        assert_eq!(find_furthest_pipe_from_start(&map), Ok(4));
 
         let map_complex = parse_map(INPUT_COMPLEX).unwrap();
-        assert_eq!(find_furthest_pipe_from_start(&map_complex), 8);
+        assert_eq!(find_furthest_pipe_from_start(&map_complex), Ok(8));
+    }
+
+    #[test]
+    fn test_trace_loop() {
+        let map = parse_map(INPUT).unwrap();
+        assert_eq!(
+            map.trace_loop(),
+            Ok(vec![(1, 1), (1, 2), (1, 3), (2, 3), (3, 3), (3, 2), (3, 1), (2, 1)])
+        );
+        assert_eq!(count_inside_loop_shoelace(&map), Ok(1));
+        assert_eq!(count_inside_loop(&map), Ok(1));
+    }
+
+    #[test]
+    fn test_trace_loop_broken() {
+        // The loop leaves the map through the bottom edge
+        let map = parse_map("S7\n||".as_bytes()).unwrap();
+        assert_eq!(map.trace_loop(), Err(Error::BrokenLoop { location: (0, 1) }));
+
+        // The loop runs into ground
+        let map = parse_map("S-7\n|..\nL-J".as_bytes()).unwrap();
+        assert_eq!(map.trace_loop(), Err(Error::BrokenLoop { location: (2, 1) }));
     }
 
     #[test]
@@ -453,8 +837,10 @@ mod tests {
         "}
         .as_bytes();
 
-        let mut map = parse_map(INPUT).unwrap();
-        assert_eq!(count_inside_loop(&mut map), 4);
+        let map = parse_map(INPUT).unwrap();
+        assert_eq!(count_inside_loop(&map), Ok(4));
+        assert_eq!(count_inside_loop_shoelace(&map), Ok(4));
+        assert_eq!(count_inside_loop_flood(&map), Ok(4));
     }
 
     #[test]
@@ -472,8 +858,10 @@ mod tests {
         "}
         .as_bytes();
 
-        let mut map = parse_map(INPUT).unwrap();
-        assert_eq!(count_inside_loop(&mut map), 4);
+        let map = parse_map(INPUT).unwrap();
+        assert_eq!(count_inside_loop(&map), Ok(4));
+        assert_eq!(count_inside_loop_shoelace(&map), Ok(4));
+        assert_eq!(count_inside_loop_flood(&map), Ok(4));
     }
 
     #[test]
@@ -492,7 +880,33 @@ mod tests {
         "}
         .as_bytes();
 
-        let mut map = parse_map(INPUT).unwrap();
-        assert_eq!(count_inside_loop(&mut map), 10);
+        let map = parse_map(INPUT).unwrap();
+        assert_eq!(count_inside_loop(&map), Ok(10));
+        assert_eq!(count_inside_loop_shoelace(&map), Ok(10));
+        assert_eq!(count_inside_loop_flood(&map), Ok(10));
     }
+
+    #[test]
+    fn test_generated() {
+        for seed in 0..10 {
+            let generated = generate_loop(&mut Rng::new(seed), 30);
+            let map = parse_map(generated.input.as_bytes()).unwrap();
+            assert_eq!(find_furthest_pipe_from_start(&map), Ok(generated.loop_length / 2));
+            assert_eq!(count_inside_loop(&map), Ok(generated.inside));
+            assert_eq!(count_inside_loop_shoelace(&map), Ok(generated.inside));
+            assert_eq!(count_inside_loop_flood(&map), Ok(generated.inside));
+            assert!(generated.inside > 0);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_parse_map_round_trip(rows in prop::collection::vec("[|LJ7F.S-]{1,10}", 1..10)) {
+            let map = parse_map(rows.join("\n").as_bytes()).unwrap();
+            let rendered = map.to_string();
+            prop_assert_eq!(&rendered, &rows.iter().map(|row| format!("{}\n", row)).collect::<String>());
+            prop_assert_eq!(parse_map(rendered.as_bytes()), Some(map));
+        }
+    }
+
 }
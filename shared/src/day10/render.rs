@@ -0,0 +1,399 @@
+//! Renders pipe maps with box drawing characters to the terminal, plain text and image files
+
+use std::{fmt::Write, path::PathBuf};
+
+use super::{find_inside_loop, parse_map, Direction, Error, Map, PipeType};
+
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+enum TileKind {
+    Ground,
+    Inside,
+    /// A pipe that is not part of the main loop
+    Pipe,
+    Loop,
+    Start,
+}
+
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+struct Tile {
+    pipe: PipeType,
+    kind: TileKind,
+    /// Steps from the start along the loop, only known for tiles on the loop
+    distance: Option<usize>,
+}
+
+/// How pipes are coloured
+#[derive(clap::ValueEnum, PartialEq, Eq, Debug, Copy, Clone)]
+pub enum ColorMode {
+    /// Highlight the main loop and the tiles inside it
+    Kind,
+    /// Colour the loop by distance from the start
+    Distance,
+}
+
+/// A map with the start pipe inferred and every tile classified, ready to be drawn in any format
+#[derive(Debug)]
+struct Rendering {
+    tiles: Vec<Vec<Tile>>,
+    max_distance: usize,
+}
+
+type Color = [u8; 3];
+
+const GROUND_COLOR: Color = [40, 40, 40];
+const INSIDE_COLOR: Color = [40, 160, 60];
+const PIPE_COLOR: Color = [110, 110, 110];
+const LOOP_COLOR: Color = [240, 200, 40];
+const START_COLOR: Color = [230, 50, 50];
+
+impl Rendering {
+    fn new(map: &Map) -> Result<Rendering, Error> {
+        let (start, start_pipe) = map.infer_start()?;
+        let vertices = map.trace_loop()?;
+        let inside = find_inside_loop(map)?;
+
+        let mut tiles: Vec<Vec<Tile>> = map
+            .data
+            .iter()
+            .zip(inside.iter())
+            .map(|(row, inside_row)| {
+                row.iter()
+                    .zip(inside_row.iter())
+                    .map(|(&pipe, &inside)| Tile {
+                        pipe,
+                        kind: match pipe {
+                            _ if inside => TileKind::Inside,
+                            PipeType::None => TileKind::Ground,
+                            _ => TileKind::Pipe,
+                        },
+                        distance: None,
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // Distance along the loop is the shorter way around, which is what a breadth first search from the start finds
+        for (index, location) in vertices.iter().enumerate() {
+            let tile = &mut tiles[location.1][location.0];
+            tile.kind = TileKind::Loop;
+            tile.distance = Some(index.min(vertices.len() - index));
+        }
+
+        let tile = &mut tiles[start.1][start.0];
+        tile.pipe = start_pipe;
+        tile.kind = TileKind::Start;
+
+        Ok(Rendering {
+            tiles,
+            max_distance: vertices.len() / 2,
+        })
+    }
+
+    fn glyph(tile: &Tile) -> char {
+        // Heavy lines for the loop so it stands out without colours
+        let on_loop = matches!(tile.kind, TileKind::Loop | TileKind::Start);
+        match (tile.pipe, on_loop) {
+            (PipeType::NS, false) => '│',
+            (PipeType::EW, false) => '─',
+            (PipeType::NE, false) => '└',
+            (PipeType::NW, false) => '┘',
+            (PipeType::SW, false) => '┐',
+            (PipeType::SE, false) => '┌',
+            (PipeType::NS, true) => '┃',
+            (PipeType::EW, true) => '━',
+            (PipeType::NE, true) => '┗',
+            (PipeType::NW, true) => '┛',
+            (PipeType::SW, true) => '┓',
+            (PipeType::SE, true) => '┏',
+            (PipeType::Start, _) => 'S',
+            (PipeType::None, _) if tile.kind == TileKind::Inside => 'I',
+            (PipeType::None, _) => '.',
+        }
+    }
+
+    fn color(&self, tile: &Tile, mode: ColorMode) -> Color {
+        match (tile.kind, mode) {
+            (TileKind::Ground, _) => GROUND_COLOR,
+            (TileKind::Inside, _) => INSIDE_COLOR,
+            (TileKind::Pipe, _) => PIPE_COLOR,
+            (TileKind::Loop, ColorMode::Kind) => LOOP_COLOR,
+            (TileKind::Start, ColorMode::Kind) => START_COLOR,
+            (TileKind::Loop | TileKind::Start, ColorMode::Distance) => {
+                heat_color(tile.distance.unwrap_or(0), self.max_distance)
+            }
+        }
+    }
+
+    fn to_text(&self) -> String {
+        let mut result = String::new();
+        for row in self.tiles.iter() {
+            // Without colours the inside has to be marked on leftover pipes as well
+            result.extend(row.iter().map(|tile| match tile.kind {
+                TileKind::Inside => 'I',
+                _ => Rendering::glyph(tile),
+            }));
+            result.push('\n');
+        }
+        result
+    }
+
+    fn to_ansi(&self, mode: ColorMode) -> String {
+        let mut result = String::new();
+        for row in self.tiles.iter() {
+            for tile in row.iter() {
+                let [r, g, b] = self.color(tile, mode);
+                let bold = if tile.kind == TileKind::Start { "1;" } else { "" };
+                write!(result, "\x1b[{}38;2;{};{};{}m{}", bold, r, g, b, Rendering::glyph(tile)).unwrap();
+            }
+            result.push_str("\x1b[0m\n");
+        }
+        result
+    }
+
+    /// Draws every tile as a square of scale by scale pixels, with the pipe as a line through its center
+    fn to_image(&self, mode: ColorMode, scale: usize) -> Image {
+        let height = self.tiles.len();
+        let width = self.tiles.iter().map(|row| row.len()).max().unwrap_or(0);
+        let mut image = Image::new(width * scale, height * scale);
+
+        let center = scale / 2;
+        for (y, row) in self.tiles.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                let color = self.color(tile, mode);
+                let (left, top) = (x * scale, y * scale);
+
+                if tile.pipe == PipeType::None {
+                    if tile.kind == TileKind::Inside {
+                        for py in 0..scale {
+                            for px in 0..scale {
+                                image.set(left + px, top + py, color);
+                            }
+                        }
+                    }
+                    continue;
+                }
+
+                image.set(left + center, top + center, color);
+                for direction in tile.pipe.connections().into_iter().flatten() {
+                    for offset in 0..center {
+                        let (px, py) = match direction {
+                            Direction::Up => (center, offset),
+                            Direction::Down => (center, scale - 1 - offset),
+                            Direction::Left => (offset, center),
+                            Direction::Right => (scale - 1 - offset, center),
+                        };
+                        image.set(left + px, top + py, color);
+                    }
+                }
+            }
+        }
+
+        image
+    }
+}
+
+/// Blue for the start through green to red for the furthest point
+fn heat_color(distance: usize, max_distance: usize) -> Color {
+    let t = if max_distance == 0 { 0.0 } else { distance as f64 / max_distance as f64 };
+    let channel = |value: f64| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    [
+        channel(2.0 * t - 1.0),
+        channel(1.0 - (2.0 * t - 1.0).abs()),
+        channel(1.0 - 2.0 * t),
+    ]
+}
+
+/// RGB image with 8 bits per channel
+struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    fn new(width: usize, height: usize) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![0; width * height * 3],
+        }
+    }
+
+    fn set(&mut self, x: usize, y: usize, color: Color) {
+        let index = (y * self.width + x) * 3;
+        self.pixels[index..index + 3].copy_from_slice(&color);
+    }
+
+    fn to_ppm(&self) -> Vec<u8> {
+        let mut result = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        result.extend_from_slice(&self.pixels);
+        result
+    }
+
+    fn write_png<W: std::io::Write>(&self, output: W) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(output, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.pixels)
+    }
+}
+
+#[derive(clap::ValueEnum, PartialEq, Eq, Debug, Copy, Clone)]
+pub enum Format {
+    /// Coloured box drawing characters for the terminal
+    Ansi,
+    /// Box drawing characters without colours, the loop uses heavy lines and inside tiles are marked I
+    Text,
+    Ppm,
+    Png,
+}
+
+/// Draw a day 10 pipe map
+#[derive(clap::Args, Debug)]
+pub struct RenderArgs {
+    /// Puzzle input to draw
+    #[arg(default_value = "inputs/2023/10.txt")]
+    input: PathBuf,
+
+    #[arg(long, value_enum, default_value_t = Format::Ansi)]
+    format: Format,
+
+    #[arg(long, value_enum, default_value_t = ColorMode::Kind)]
+    colors: ColorMode,
+
+    /// File to write to, defaults to stdout for text formats and day10.ppm or day10.png for images
+    #[arg(long)]
+    output: Option<PathBuf>,
+
+    /// Size of a tile in pixels for image formats
+    #[arg(long, default_value_t = 5)]
+    scale: usize,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum RenderError {
+    #[error("failed to read or write a file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0}")]
+    Map(#[from] Error),
+    #[error("failed to encode the png: {0}")]
+    Png(#[from] png::EncodingError),
+}
+
+pub fn render(args: &RenderArgs) -> Result<(), RenderError> {
+    let input = std::fs::read(&args.input)?;
+    let map = parse_map(&input).ok_or(Error::Parse)?;
+    let rendering = Rendering::new(&map)?;
+    let scale = args.scale.max(1);
+
+    let bytes = match args.format {
+        Format::Ansi => rendering.to_ansi(args.colors).into_bytes(),
+        Format::Text => rendering.to_text().into_bytes(),
+        Format::Ppm => rendering.to_image(args.colors, scale).to_ppm(),
+        Format::Png => {
+            let mut bytes = Vec::new();
+            rendering.to_image(args.colors, scale).write_png(&mut bytes)?;
+            bytes
+        }
+    };
+
+    let output = match args.format {
+        Format::Ansi | Format::Text => args.output.clone(),
+        Format::Ppm => Some(args.output.clone().unwrap_or("day10.ppm".into())),
+        Format::Png => Some(args.output.clone().unwrap_or("day10.png".into())),
+    };
+
+    match output {
+        Some(path) => std::fs::write(path, bytes)?,
+        None => std::io::Write::write_all(&mut std::io::stdout(), &bytes)?,
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    const INPUT: &[u8] = indoc! {"
+        .....
+        .S-7.
+        .|.|.
+        .L-J.
+        ..F7.
+    "}
+    .as_bytes();
+
+    #[test]
+    fn test_to_text() {
+        let rendering = Rendering::new(&parse_map(INPUT).unwrap()).unwrap();
+        assert_eq!(
+            rendering.to_text(),
+            indoc! {"
+                .....
+                .┏━┓.
+                .┃I┃.
+                .┗━┛.
+                ..┌┐.
+            "}
+        );
+    }
+
+    #[test]
+    fn test_distances() {
+        let rendering = Rendering::new(&parse_map(INPUT).unwrap()).unwrap();
+        let distances: Vec<Option<usize>> = rendering.tiles[1].iter().map(|tile| tile.distance).collect();
+        assert_eq!(distances, [None, Some(0), Some(1), Some(2), None]);
+        assert_eq!(rendering.tiles[3][3].distance, Some(4));
+        assert_eq!(rendering.max_distance, 4);
+        assert_eq!(rendering.tiles[1][1].kind, TileKind::Start);
+    }
+
+    #[test]
+    fn test_to_ansi() {
+        let rendering = Rendering::new(&parse_map(INPUT).unwrap()).unwrap();
+        let ansi = rendering.to_ansi(ColorMode::Kind);
+        assert_eq!(ansi.lines().count(), 5);
+        assert!(ansi.contains("\x1b[38;2;240;200;40m━"));
+        assert!(ansi.contains("\x1b[1;38;2;230;50;50m┏"));
+
+        let heatmap = rendering.to_ansi(ColorMode::Distance);
+        assert!(heatmap.contains("\x1b[1;38;2;0;0;255m┏"));
+        assert!(heatmap.contains("\x1b[38;2;255;0;0m┛"));
+    }
+
+    #[test]
+    fn test_heat_color() {
+        assert_eq!(heat_color(0, 10), [0, 0, 255]);
+        assert_eq!(heat_color(5, 10), [0, 255, 0]);
+        assert_eq!(heat_color(10, 10), [255, 0, 0]);
+        assert_eq!(heat_color(0, 0), [0, 0, 255]);
+    }
+
+    #[test]
+    fn test_to_image() {
+        let rendering = Rendering::new(&parse_map(INPUT).unwrap()).unwrap();
+        let image = rendering.to_image(ColorMode::Kind, 3);
+        assert_eq!((image.width, image.height), (15, 15));
+
+        // The start is an F pipe: center, right and bottom pixels are drawn
+        let pixel = |x: usize, y: usize| image.pixels[(y * 15 + x) * 3..(y * 15 + x) * 3 + 3].to_vec();
+        assert_eq!(pixel(4, 4), START_COLOR);
+        assert_eq!(pixel(5, 4), START_COLOR);
+        assert_eq!(pixel(4, 5), START_COLOR);
+        assert_eq!(pixel(3, 4), [0, 0, 0]);
+        assert_eq!(pixel(4, 3), [0, 0, 0]);
+
+        // The inside tile is filled
+        assert_eq!(pixel(6, 6), INSIDE_COLOR);
+
+        let ppm = image.to_ppm();
+        assert!(ppm.starts_with(b"P6\n15 15\n255\n"));
+        assert_eq!(ppm.len(), "P6\n15 15\n255\n".len() + 15 * 15 * 3);
+
+        let mut png = Vec::new();
+        image.write_png(&mut png).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+    }
+}
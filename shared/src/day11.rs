@@ -1,26 +1,37 @@
-use std::io::empty;
+use crate::{generate::Rng, visualize::{self, Frame}};
 
 
 
-struct Map{
+
+pub struct Map{
     galaxies: Vec<(usize, usize)>,
 }
 
-impl Map{
-    fn empty() -> Self{
-        Self { galaxies: vec![] }
+/// Draws the image up to the last galaxy in either direction, empty space past it does not change any distance
+impl std::fmt::Display for Map{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        let width = self.galaxies.iter().map(|galaxy| galaxy.0 + 1).max().unwrap_or(0);
+        let height = self.galaxies.iter().map(|galaxy| galaxy.1 + 1).max().unwrap_or(0);
+        let mut rows = vec![vec!['.'; width]; height];
+        for (x, y) in self.galaxies.iter(){
+            rows[*y][*x] = '#';
+        }
+        for row in rows{
+            writeln!(f, "{}", row.into_iter().collect::<String>())?;
+        }
+        Ok(())
     }
 }
 
-fn parse_map<R: std::io::BufRead>(input: R) -> Option<Map>{
+pub fn parse_map(input: &[u8]) -> Option<Map>{
     let mut galaxies = Vec::new();
-    for (y, line) in input.lines().enumerate(){
-        for (x, char) in line.ok()?.chars().enumerate(){
-            match char{
-                '#' => {
+    for (y, line) in crate::input::lines(input).enumerate(){
+        for (x, byte) in line.iter().enumerate(){
+            match byte{
+                b'#' => {
                     galaxies.push((x, y));
                 },
-                '.' => (),
+                b'.' => (),
                 _ => return None,
             }
         }
@@ -29,64 +40,190 @@ fn parse_map<R: std::io::BufRead>(input: R) -> Option<Map>{
     Some(Map{ galaxies })
 }
 
-fn cosmic_expansion(input: &Map, empty_scale: usize) -> Map{
-    let (max_x, max_y) = match input.galaxies.iter().copied().reduce(|a, b| (a.0.max(b.0), a.1.max(b.1))){
-        Some(a) => a,
-        None => return Map::empty(),
-    };
-
-    // Go over the galaxies and remember which rows and columns contain anything
-    let mut row_has_galaxy = vec![false; max_x + 1];
-    let mut column_has_galaxy = vec![false; max_y + 1];
-    for galaxy in input.galaxies.iter(){
-        row_has_galaxy[galaxy.1] = true;
-        column_has_galaxy[galaxy.0] = true;
-    }
-
-    // Accumulate an offset for each row and cooumn
-    let row_offset : Vec<usize> = row_has_galaxy.iter().scan(0, |state, value| {
-        let state_before = *state;
-        if !value{ *state += empty_scale; } else { *state += 1; }
-        Some(state_before)
-    }).collect();
-    let column_offset : Vec<usize> = column_has_galaxy.iter().scan(0, |state, value| {
-        let state_before = *state;
-        if !value{ *state += empty_scale; } else { *state += 1; }
-        Some(state_before)
-    }).collect();
+/// Size that a single empty row or column grows to, separately for both axes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scale{
+    /// Width of every empty column
+    pub x: usize,
+    /// Height of every empty row
+    pub y: usize,
+}
+
+impl Scale{
+    /// Empty rows and columns grow alike, as in the puzzle
+    pub fn uniform(scale: usize) -> Scale{
+        Scale{ x: scale, y: scale }
+    }
+}
+
+/// Which coordinates along one axis hold at least one galaxy
+fn occupancy(coordinates: impl Iterator<Item = usize> + Clone) -> Vec<bool>{
+    let mut occupied = vec![false; coordinates.clone().max().map_or(0, |max| max + 1)];
+    for coordinate in coordinates{
+        occupied[coordinate] = true;
+    }
+    occupied
+}
+
+/// New position of every coordinate along one axis once empty ones grow to scale, None on overflow
+fn expand_axis(occupied: &[bool], scale: usize) -> Option<Vec<usize>>{
+    let mut offsets = Vec::with_capacity(occupied.len());
+    let mut offset = 0usize;
+    for occupied in occupied{
+        offsets.push(offset);
+        offset = offset.checked_add(if *occupied { 1 } else { scale })?;
+    }
+    Some(offsets)
+}
+
+/// Grows every empty row and column, None when the expanded coordinates do not fit in a usize
+pub fn cosmic_expansion(input: &Map, scale: Scale) -> Option<Map>{
+    let column_has_galaxy = occupancy(input.galaxies.iter().map(|galaxy| galaxy.0));
+    let row_has_galaxy = occupancy(input.galaxies.iter().map(|galaxy| galaxy.1));
+
+    let column_offset = expand_axis(&column_has_galaxy, scale.x)?;
+    let row_offset = expand_axis(&row_has_galaxy, scale.y)?;
 
     let galaxies : Vec<(usize, usize)> = input.galaxies.iter().copied().map(|galaxy| (column_offset[galaxy.0], row_offset[galaxy.1])).collect();
-    Map{ galaxies }
+    let result = Map{ galaxies };
+
+    visualize::emit(|| galaxy_frame(format!("Columns without galaxies grow to {}, rows to {}", scale.x, scale.y), input, &column_has_galaxy, &row_has_galaxy));
+    // The size check walks every galaxy, so it only runs while something is watching
+    if visualize::enabled() && result.galaxies.iter().all(|galaxy| galaxy.0 < MAX_FRAME_SIZE && galaxy.1 < MAX_FRAME_SIZE){
+        visualize::emit(|| galaxy_frame("Expanded universe".into(), &result, &[], &[]));
+    }
+
+    Some(result)
+}
+
+/// Expanded maps larger than this are not drawn
+const MAX_FRAME_SIZE: usize = 500;
+
+/// Draws the galaxies, with the columns and rows that are not marked as containing one muted
+fn galaxy_frame(caption: String, map: &Map, column_has_galaxy: &[bool], row_has_galaxy: &[bool]) -> Frame{
+    let width = map.galaxies.iter().map(|galaxy| galaxy.0 + 1).max().unwrap_or(0).max(column_has_galaxy.len());
+    let height = map.galaxies.iter().map(|galaxy| galaxy.1 + 1).max().unwrap_or(0).max(row_has_galaxy.len());
+
+    let mut frame = Frame::new(caption);
+    frame.rows = vec![vec!['.'; width]; height];
+    for (x, has_galaxy) in column_has_galaxy.iter().enumerate(){
+        if !has_galaxy{
+            (0..height).for_each(|y| frame.highlight(x, y, visualize::MUTED));
+        }
+    }
+    for (y, has_galaxy) in row_has_galaxy.iter().enumerate(){
+        if !has_galaxy{
+            frame.highlight_span(0, y, width, visualize::MUTED);
+        }
+    }
+    for galaxy in map.galaxies.iter(){
+        frame.rows[galaxy.1][galaxy.0] = '#';
+        frame.highlight(galaxy.0, galaxy.1, visualize::HIGHLIGHT);
+    }
+
+    frame
+}
+
+impl Map{
+    /// Manhattan distance between two galaxies, numbered in reading order from 0
+    pub fn distance(&self, a: usize, b: usize) -> Option<usize>{
+        let (a, b) = (self.galaxies.get(a)?, self.galaxies.get(b)?);
+        a.0.abs_diff(b.0).checked_add(a.1.abs_diff(b.1))
+    }
 }
 
-fn sum_shortest_paths(map: &Map) -> usize{
-    let mut sum = 0;
-    for i in 0..map.galaxies.len(){
-        for j in (i+1)..map.galaxies.len(){
-            let a = map.galaxies[i];
-            let b = map.galaxies[j];
-            sum += b.0.abs_diff(a.0) + b.1.abs_diff(a.1);
+/// Sum of the distances between all pairs of coordinates along one axis.
+/// Once sorted, every coordinate is the far end of a pair with each coordinate before it.
+fn sum_axis_distances(mut coordinates: Vec<usize>) -> Option<usize>{
+    coordinates.sort_unstable();
+
+    let mut sum = 0usize;
+    let mut prefix = 0usize;
+    for (index, coordinate) in coordinates.into_iter().enumerate(){
+        sum = sum.checked_add(coordinate.checked_mul(index)? - prefix)?;
+        prefix = prefix.checked_add(coordinate)?;
+    }
+    Some(sum)
+}
+
+/// Sum of the distances between all pairs of galaxies, None on overflow
+pub fn sum_shortest_paths(map: &Map) -> Option<usize>{
+    let x = sum_axis_distances(map.galaxies.iter().map(|galaxy| galaxy.0).collect())?;
+    let y = sum_axis_distances(map.galaxies.iter().map(|galaxy| galaxy.1).collect())?;
+    x.checked_add(y)
+}
+
+/// Reference for the expanded distances that walks from each galaxy to every other one,
+/// counting every empty row and column along the way as scale steps
+fn sum_shortest_paths_reference(map: &Map, scale: usize) -> Option<usize>{
+    let column_has_galaxy = occupancy(map.galaxies.iter().map(|galaxy| galaxy.0));
+    let row_has_galaxy = occupancy(map.galaxies.iter().map(|galaxy| galaxy.1));
+    let steps = |occupied: &[bool], a: usize, b: usize| (a.min(b)..a.max(b)).map(|index| if occupied[index] { 1 } else { scale }).sum::<usize>();
+
+    let mut sum = 0usize;
+    for (index, a) in map.galaxies.iter().enumerate(){
+        for b in map.galaxies[index + 1..].iter(){
+            sum = sum.checked_add(steps(&column_has_galaxy, a.0, b.0))?.checked_add(steps(&row_has_galaxy, a.1, b.1))?;
         }
     }
+    Some(sum)
+}
 
-    sum
+/// Sum of the distances between all galaxies after every empty row and column grew to scale
+pub fn sum_expanded_distances(map: &Map, scale: Scale) -> Option<usize>{
+    sum_shortest_paths(&cosmic_expansion(map, scale)?)
 }
 
-#[aoc_2023_markup::aoc_task(2023, 11, 1)]
-fn part1<R: std::io::BufRead>(input: R) -> Option<usize>{
-    let map = cosmic_expansion(&parse_map(input)?, 2);
-    Some(sum_shortest_paths(&map))
+#[aoc_2023_markup::aoc_task(2023, 11, 1, parse = parse_map, solve = |map| sum_expanded_distances(map, Scale::uniform(2)))]
+pub fn part1(input: &[u8]) -> Option<usize>{
+    sum_expanded_distances(&parse_map(input)?, Scale::uniform(2))
 }
 
-#[aoc_2023_markup::aoc_task(2023, 11, 2)]
-fn part2<R: std::io::BufRead>(input: R) -> Option<usize>{
-    let map = cosmic_expansion(&parse_map(input)?, 1000000);
-    Some(sum_shortest_paths(&map))
+#[aoc_2023_markup::aoc_task(2023, 11, 2, parse = parse_map, solve = |map| sum_expanded_distances(map, Scale::uniform(1000000)))]
+pub fn part2(input: &[u8]) -> Option<usize>{
+    sum_expanded_distances(&parse_map(input)?, Scale::uniform(1000000))
+}
+
+#[aoc_2023_markup::aoc_task(2023, 11, 1, "reference")]
+fn part1_reference(input: &[u8]) -> Option<usize>{
+    sum_shortest_paths_reference(&parse_map(input)?, 2)
+}
+
+#[aoc_2023_markup::aoc_task(2023, 11, 2, "reference")]
+fn part2_reference(input: &[u8]) -> Option<usize>{
+    sum_shortest_paths_reference(&parse_map(input)?, 1000000)
+}
+
+/// Square image of size by size with sparse galaxies, about a fifth of the rows and columns are left empty
+pub fn generate(rng: &mut Rng, size: usize) -> String{
+    let rows: Vec<bool> = (0..size).map(|_| rng.chance(0.8)).collect();
+    let columns: Vec<bool> = (0..size).map(|_| rng.chance(0.8)).collect();
+
+    let mut result = String::new();
+    for row in rows.iter(){
+        for column in columns.iter(){
+            result.push(if *row && *column && rng.chance(0.1) { '#' } else { '.' });
+        }
+        result.push('\n');
+    }
+    result
+}
+
+pub fn fuzz(data: &[u8]){
+    let _ = parse_map(data);
+}
+
+/// Rewrites the image without the empty space past its last galaxies
+pub fn normalize(data: &[u8]) -> Option<String>{
+    parse_map(data).map(|map| map.to_string())
 }
 
 #[cfg(test)]
 mod tests{
     use indoc::indoc;
+    use itertools::Itertools;
+    use proptest::prelude::*;
+    use crate::generate::Rng;
     use super::*;
 
     const INPUT : &[u8] = indoc!{"
@@ -115,13 +252,13 @@ mod tests{
             (0, 9),
             (4, 9)
         ]);
-
+        assert_eq!(parse_map(INPUT).unwrap().to_string().as_bytes(), INPUT);
     }
 
     #[test]
     fn test_cosmic_expansion(){
         let map = parse_map(INPUT).unwrap();
-        let map = cosmic_expansion(&map, 2);
+        let map = cosmic_expansion(&map, Scale::uniform(2)).unwrap();
         assert_eq!(map.galaxies, [
             (4, 0),
             (9, 1),
@@ -135,11 +272,113 @@ mod tests{
         ])
     }
 
+    #[test]
+    fn test_expansion_frames(){
+        let map = parse_map(INPUT).unwrap();
+        let (_, frames) = visualize::record(|| cosmic_expansion(&map, Scale::uniform(2)).unwrap());
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].rows.len(), 10);
+        assert_eq!(frames[1].rows.len(), 12);
+        assert_eq!(frames[1].rows[0].iter().collect::<String>(), "....#........");
+
+        let (_, frames) = visualize::record(|| cosmic_expansion(&map, Scale::uniform(1000000)).unwrap());
+        assert_eq!(frames.len(), 1);
+    }
+
     #[test]
     fn test_sum_shortest_path(){
-        let map = cosmic_expansion(&parse_map(INPUT).unwrap(), 2);
+        let map = cosmic_expansion(&parse_map(INPUT).unwrap(), Scale::uniform(2)).unwrap();
         let result = sum_shortest_paths(&map);
-        assert_eq!(result, 374);
+        assert_eq!(result, Some(374));
+
+        let map = cosmic_expansion(&parse_map(INPUT).unwrap(), Scale::uniform(10)).unwrap();
+        assert_eq!(sum_shortest_paths(&map), Some(1030));
+
+        let map = cosmic_expansion(&parse_map(INPUT).unwrap(), Scale::uniform(100)).unwrap();
+        assert_eq!(sum_shortest_paths(&map), Some(8410));
+
+        let map = parse_map(INPUT).unwrap();
+        assert_eq!(sum_shortest_paths_reference(&map, 2), Some(374));
+        assert_eq!(sum_shortest_paths_reference(&map, 10), Some(1030));
+        assert_eq!(sum_shortest_paths_reference(&map, 100), Some(8410));
+    }
+
+    #[test]
+    fn test_distance(){
+        let map = cosmic_expansion(&parse_map(INPUT).unwrap(), Scale::uniform(2)).unwrap();
+        assert_eq!(map.distance(4, 8), Some(9));
+        assert_eq!(map.distance(0, 6), Some(15));
+        assert_eq!(map.distance(2, 5), Some(17));
+        assert_eq!(map.distance(7, 8), Some(5));
+        assert_eq!(map.distance(8, 7), Some(5));
+        assert_eq!(map.distance(0, 9), None);
+    }
+
+    #[test]
+    fn test_cosmic_expansion_non_square(){
+        // Wider than tall and taller than wide, which used to mix up the axes
+        let map = parse_map(indoc!{"
+            #...#..
+            .......
+        "}.as_bytes()).unwrap();
+        let map = cosmic_expansion(&map, Scale::uniform(2)).unwrap();
+        assert_eq!(map.galaxies, [(0, 0), (7, 0)]);
+
+        let map = parse_map(indoc!{"
+            .#
+            ..
+            ..
+            .#
+        "}.as_bytes()).unwrap();
+        let map = cosmic_expansion(&map, Scale::uniform(2)).unwrap();
+        assert_eq!(map.galaxies, [(2, 0), (2, 5)]);
+    }
+
+    #[test]
+    fn test_cosmic_expansion_per_axis(){
+        let map = parse_map(INPUT).unwrap();
+        let map = cosmic_expansion(&map, Scale{ x: 1, y: 3 }).unwrap();
+        assert_eq!(map.galaxies[3], (6, 6));
+        assert_eq!(map.galaxies[5], (9, 8));
+
+        // Rows and columns add their growth independently of each other
+        let sum = |scale| sum_expanded_distances(&parse_map(INPUT).unwrap(), scale).unwrap();
+        assert_eq!(sum(Scale{ x: 1, y: 3 }) + sum(Scale{ x: 3, y: 1 }), sum(Scale::uniform(3)) + sum(Scale::uniform(1)));
+        assert_eq!(sum(Scale{ x: 2, y: 2 }), 374);
+
+        let scale = Scale{ x: usize::MAX, y: 1 };
+        assert!(cosmic_expansion(&parse_map(INPUT).unwrap(), scale).is_none());
+    }
+
+    proptest!{
+        #[test]
+        fn test_sum_shortest_paths_matches_pairs(galaxies in prop::collection::vec((0usize..1000, 0usize..1000), 0..40)){
+            let map = Map{ galaxies };
+            let pairs = (0..map.galaxies.len())
+                .tuple_combinations()
+                .map(|(a, b)| map.distance(a, b).unwrap())
+                .sum::<usize>();
+            prop_assert_eq!(sum_shortest_paths(&map), Some(pairs));
+        }
+    }
+
+
+    #[test]
+    fn test_generated(){
+        let input = generate(&mut Rng::new(11), 60);
+        assert_eq!(part1(input.as_bytes()), part1_reference(input.as_bytes()));
+        assert_eq!(part2(input.as_bytes()), part2_reference(input.as_bytes()));
+        assert!(part1(input.as_bytes()).is_some_and(|sum| sum > 0));
+    }
+
+    proptest!{
+        #[test]
+        fn test_parse_map_round_trip(rows in prop::collection::vec("[.#]{0,12}", 0..10)){
+            let map = parse_map(rows.join("\n").as_bytes()).unwrap();
+            let rendered = map.to_string();
+            prop_assert_eq!(parse_map(rendered.as_bytes()).unwrap().galaxies, map.galaxies);
+            prop_assert_eq!(normalize(rendered.as_bytes()), Some(rendered));
+        }
     }
 
 }
\ No newline at end of file
@@ -1,132 +1,275 @@
-use std::{io::{BufRead, BufReader}, fs::File};
 use thiserror::Error;
 use lazy_static::lazy_static;
 use regex::Regex;
 
-#[derive(Debug, Eq, PartialEq, Clone, Copy)]
-struct DiceCount{
-    red: u32,
-    green: u32,
-    blue: u32
+use crate::generate::Rng;
+
+/// Names of the dice colours in the bag, counts of dice are stored in this order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Colors(Vec<String>);
+
+impl Colors{
+    pub fn new<S: Into<String>>(names: impl IntoIterator<Item = S>) -> Colors{
+        Colors(names.into_iter().map(Into::into).collect())
+    }
+
+    pub fn standard() -> Colors{
+        Colors::new(["red", "green", "blue"])
+    }
+
+    fn index(&self, name: &str) -> Option<usize>{
+        self.0.iter().position(|color| color == name)
+    }
+
+    /// Bag holding the given number of dice of each colour, colours left out have no dice in it.
+    /// None when a colour is not one of these.
+    pub fn bag(&self, dice: &[(&str, u32)]) -> Option<DiceCount>{
+        let mut counts = vec![0; self.0.len()];
+        for (name, count) in dice{
+            counts[self.index(name)?] = *count;
+        }
+        Some(DiceCount(counts))
+    }
 }
 
-struct Game{
+/// Number of dice of every colour
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct DiceCount(Vec<u32>);
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Game{
     id: u32,
     records: Vec<DiceCount>
 }
 
-#[derive(Error, Debug)]
-enum ParseGameError{
-    #[error("any")]
-    Any
+impl Game{
+    /// Renders the game as a line of the puzzle input, the colours give the names of the counts
+    pub fn display<'a>(&'a self, colors: &'a Colors) -> GameDisplay<'a>{
+        GameDisplay{ game: self, colors }
+    }
 }
 
-fn parse_roll(roll: &str) -> DiceCount{
-    let mut record = DiceCount{
-        red: 0,
-        green: 0,
-        blue: 0
-    };
+pub struct GameDisplay<'a>{
+    game: &'a Game,
+    colors: &'a Colors,
+}
 
-    let entries = roll.split(", ");
-    for entry in entries{
-        let mut segments = entry.split(' ');
-        let test = segments.next().unwrap();
-        let number = test.parse::<u32>().unwrap();
-        let ty = segments.next().unwrap();
+impl std::fmt::Display for GameDisplay<'_>{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        write!(f, "Game {}: ", self.game.id)?;
+        for (index, roll) in self.game.records.iter().enumerate(){
+            if index > 0{
+                write!(f, "; ")?;
+            }
 
-        if ty == "red"{
-            record.red += number;
-        }
-        else if ty == "green"{
-            record.green += number;
-        }
-        else if ty == "blue"{
-            record.blue += number;
+            // Colours that were not rolled are left out, unless that would leave the roll empty
+            let mut entries = roll.0.iter().zip(self.colors.0.iter()).filter(|(count, _)| **count > 0).peekable();
+            if entries.peek().is_none(){
+                write!(f, "0 {}", self.colors.0.first().map_or("", String::as_str))?;
+            }
+            for (position, (count, color)) in entries.enumerate(){
+                write!(f, "{}{} {}", if position > 0 { ", " } else { "" }, count, color)?;
+            }
         }
-        else{
-            // error
+        Ok(())
+    }
+}
+
+/// Errors carry the line they were found on, counting from 1
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ParseGameError{
+    #[error("line {line} is not valid UTF-8")]
+    NotUtf8{ line: usize },
+    #[error("line {line}: expected \"Game <id>: <rolls>\"")]
+    BadHeader{ line: usize },
+    #[error("line {line}: expected \"<count> <colour>\", found \"{entry}\"")]
+    BadEntry{ line: usize, entry: String },
+    #[error("line {line}: {count} is not a valid number of dice")]
+    BadCount{ line: usize, count: String },
+    #[error("line {line}: there are no {color} dice in the bag")]
+    UnknownColor{ line: usize, color: String },
+    #[error("line {line}: {color} appears more than once in the same roll")]
+    DuplicateColor{ line: usize, color: String },
+}
+
+fn parse_roll(roll: &str, line: usize, colors: &Colors) -> Result<DiceCount, ParseGameError>{
+    let mut record = DiceCount(vec![0; colors.0.len()]);
+    let mut seen = vec![false; colors.0.len()];
+
+    for entry in roll.split(", "){
+        let (count, color) = entry.split_once(' ').ok_or_else(|| ParseGameError::BadEntry{ line, entry: entry.into() })?;
+        let count = count.parse::<u32>().map_err(|_| ParseGameError::BadCount{ line, count: count.into() })?;
+        let index = colors.index(color).ok_or_else(|| ParseGameError::UnknownColor{ line, color: color.into() })?;
+        if std::mem::replace(&mut seen[index], true){
+            return Err(ParseGameError::DuplicateColor{ line, color: color.into() });
         }
+
+        record.0[index] = count;
     }
 
-    record
+    Ok(record)
 }
 
-fn parse_game(line: &str) -> Result<Game, ParseGameError>{
+fn parse_game(input: &str, line: usize, colors: &Colors) -> Result<Game, ParseGameError>{
     lazy_static!{
-        static ref RE: Regex = Regex::new(r"Game (\d+): (.*)").unwrap();
+        static ref RE: Regex = Regex::new(r"^Game (\d+): (.*)$").unwrap();
     }
 
-    let captures = RE.captures(line).unwrap();
-    let id = captures[1].parse::<u32>().unwrap();
+    let captures = RE.captures(input.trim()).ok_or(ParseGameError::BadHeader{ line })?;
+    let id = captures[1].parse::<u32>().map_err(|_| ParseGameError::BadHeader{ line })?;
 
-    let records = captures[2].split("; ").map(parse_roll).collect();
+    let records = captures[2].split("; ").map(|roll| parse_roll(roll, line, colors)).collect::<Result<_, _>>()?;
     Ok(Game{ id, records})
 }
 
-fn parse_games<R: BufRead>(reader: R) -> Result<Vec<Game>, ParseGameError>{
-    reader.lines().map(|line| parse_game(&line.map_err(|e| ParseGameError::Any)?)).collect()
+pub fn parse_games(input: &[u8], colors: &Colors) -> Result<Vec<Game>, ParseGameError>{
+    crate::input::lines(input)
+        .enumerate()
+        .map(|(index, line)|{
+            let line = std::str::from_utf8(line).map_err(|_| ParseGameError::NotUtf8{ line: index + 1 })?;
+            parse_game(line, index + 1, colors)
+        })
+        .collect()
 }
 
 fn has_enough_dice(available_dice: &DiceCount, roll: &DiceCount) -> bool{
-    roll.red <= available_dice.red &&
-        roll.green <= available_dice.green &&
-        roll.blue <= available_dice.blue
+    roll.0.iter().zip(available_dice.0.iter()).all(|(rolled, available)| rolled <= available)
 }
 
-fn add_possible_games(games: &[Game], available_dice: &DiceCount) -> u32{
+/// Sum of the ids of the games that were possible with the given dice in the bag.
+/// Panics when the bag and the games were read with a different number of colours.
+pub fn sum_possible_games(games: &[Game], available_dice: &DiceCount) -> u32{
+    let rolls = games.iter().flat_map(|game| game.records.iter());
+    assert!(rolls.clone().all(|roll| roll.0.len() == available_dice.0.len()), "the bag and the games have different colours");
+
     let possible_games = games.iter().filter(|game| game.records.iter().all(|roll| has_enough_dice(available_dice, roll)));
     possible_games.map(|game| game.id).reduce(|a, b| a + b).unwrap_or(0)
 }
 
-#[aoc_2023_markup::aoc_task(2023, 2, 1)]
-fn part1(input: &mut dyn BufRead) -> Result<u32, ParseGameError>{
-    let input = parse_games(input);
-
-    let available_dice = DiceCount{
-        red: 12,
-        green: 13,
-        blue: 14
-    };
+/// The dice in the bag of part 1
+pub fn standard_bag() -> DiceCount{
+    Colors::standard().bag(&[("red", 12), ("green", 13), ("blue", 14)]).unwrap()
+}
 
-    input.map(|games| add_possible_games(&games, &available_dice))
+#[aoc_2023_markup::aoc_task(2023, 2, 1, parse = |input| parse_games(input, &Colors::standard()), solve = |games| sum_possible_games(games, &standard_bag()))]
+pub fn part1(input: &[u8]) -> Result<u32, ParseGameError>{
+    let input = parse_games(input, &Colors::standard());
+    input.map(|games| sum_possible_games(&games, &standard_bag()))
 }
 
-fn calculate_power(game: &Game) -> u32{
+/// Product of the fewest dice of every colour the game could have been played with
+pub fn calculate_power(game: &Game) -> u32{
     game.records
         .iter()
-        .copied()
-        .reduce(|a, b|{
-            DiceCount{
-                red: a.red.max(b.red),
-                green: a.green.max(b.green),
-                blue: a.blue.max(b.blue),
-            }
-        })
-        .map_or(0, |counts| counts.red * counts.green * counts.blue)
+        .cloned()
+        .reduce(|a, b| DiceCount(a.0.iter().zip(b.0.iter()).map(|(a, b)| *a.max(b)).collect()))
+        .map_or(0, |counts| counts.0.iter().product())
 }
 
-#[aoc_2023_markup::aoc_task(2023, 2, 2)]
-fn part2(input: &mut dyn BufRead) -> Result<u32, ParseGameError>{
-    let input = parse_games(input);
-    input.map(|games| games.iter().map(calculate_power).sum::<u32>())
+pub fn sum_powers(games: &[Game]) -> u32{
+    games.iter().map(calculate_power).sum()
+}
+
+#[aoc_2023_markup::aoc_task(2023, 2, 2, parse = |input| parse_games(input, &Colors::standard()), solve = sum_powers)]
+pub fn part2(input: &[u8]) -> Result<u32, ParseGameError>{
+    let input = parse_games(input, &Colors::standard());
+    input.map(|games| sum_powers(&games))
+}
+
+/// Games of one to six rolls, each showing up to 20 dice of some of the standard colours
+pub fn generate(rng: &mut Rng, size: usize) -> String{
+    let colors = Colors::standard();
+    let mut result = String::new();
+    for id in 1..=size{
+        let rolls: Vec<String> = (0..rng.range(1..7))
+            .map(|_|{
+                let mut order: Vec<&String> = colors.0.iter().collect();
+                rng.shuffle(&mut order);
+                order.truncate(rng.range(1..order.len() + 1));
+                order.iter().map(|color| format!("{} {}", rng.range(1..21), color)).collect::<Vec<_>>().join(", ")
+            })
+            .collect();
+        result.push_str(&format!("Game {}: {}\n", id, rolls.join("; ")));
+    }
+    result
+}
+
+/// Parses the games with the standard bag
+pub fn fuzz(data: &[u8]){
+    let _ = parse_games(data, &Colors::standard());
+}
+
+/// Rewrites the games with the colours of every roll in the standard order
+pub fn normalize(data: &[u8]) -> Option<String>{
+    let colors = Colors::standard();
+    let games = parse_games(data, &colors).ok()?;
+    Some(games.iter().map(|game| format!("{}\n", game.display(&colors))).collect())
 }
 
 #[cfg(test)]
 mod tests{
+    use proptest::prelude::*;
+    use crate::generate::Rng;
     use super::*;
 
     #[test]
     fn test_parse_game(){
-        let game = parse_game("Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green").unwrap();
+        let game = parse_game("Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green", 1, &Colors::standard()).unwrap();
         assert_eq!(game.id, 1);
         assert_eq!(game.records, [
-            DiceCount{ blue: 3, red: 4, green: 0 },
-            DiceCount{ red: 1, green: 2, blue: 6 },
-            DiceCount{green: 2, red: 0, blue: 0 },
+            DiceCount(vec![4, 0, 3]),
+            DiceCount(vec![1, 2, 6]),
+            DiceCount(vec![0, 2, 0]),
         ]);
     }
 
+    #[test]
+    fn test_parse_errors(){
+        let colors = Colors::standard();
+        let error = |input| parse_game(input, 7, &colors).err();
+        assert_eq!(error("Game one: 3 blue"), Some(ParseGameError::BadHeader{ line: 7 }));
+        assert_eq!(error("3 blue, 4 red"), Some(ParseGameError::BadHeader{ line: 7 }));
+        assert_eq!(error("Game 1: 3blue"), Some(ParseGameError::BadEntry{ line: 7, entry: "3blue".into() }));
+        assert_eq!(error("Game 1: x blue"), Some(ParseGameError::BadCount{ line: 7, count: "x".into() }));
+        assert_eq!(error("Game 1: 3 purple"), Some(ParseGameError::UnknownColor{ line: 7, color: "purple".into() }));
+        assert_eq!(error("Game 1: 2 red; 3 blue, 1 blue"), Some(ParseGameError::DuplicateColor{ line: 7, color: "blue".into() }));
+
+        let games = parse_games("Game 1: 1 red\nGame 2: 1 red, 1 red".as_bytes(), &colors);
+        assert_eq!(games.err().map(|error| error.to_string()).as_deref(), Some("line 2: red appears more than once in the same roll"));
+    }
+
+    #[test]
+    fn test_custom_colors(){
+        let colors = Colors::new(["yellow", "red"]);
+        let games = parse_games("Game 1: 3 yellow; 2 red\nGame 2: 5 yellow, 1 red".as_bytes(), &colors).unwrap();
+        assert_eq!(games[1].records, [DiceCount(vec![5, 1])]);
+        assert_eq!(sum_possible_games(&games, &colors.bag(&[("yellow", 4), ("red", 2)]).unwrap()), 1);
+        assert_eq!(games.iter().map(calculate_power).collect::<Vec<_>>(), [6, 5]);
+
+        assert!(parse_games("Game 1: 3 blue".as_bytes(), &colors).is_err());
+    }
+
+    #[test]
+    fn test_custom_bag(){
+        let colors = Colors::new(["red", "green", "blue", "black"]);
+        let games = parse_games("Game 1: 3 black, 2 red
+Game 2: 1 blue; 1 black
+Game 3: 9 green".as_bytes(), &colors).unwrap();
+        let bag = colors.bag(&[("black", 3), ("blue", 1), ("red", 2)]).unwrap();
+        assert_eq!(bag, DiceCount(vec![2, 0, 1, 3]));
+        assert_eq!(sum_possible_games(&games, &bag), 1 + 2);
+        assert_eq!(sum_possible_games(&games, &colors.bag(&[("green", 9)]).unwrap()), 3);
+        assert_eq!(colors.bag(&[("purple", 1)]), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "different colours")]
+    fn test_bag_of_other_colors(){
+        let colors = Colors::new(["red", "green", "blue", "black"]);
+        let games = parse_games("Game 1: 3 black, 2 red".as_bytes(), &colors).unwrap();
+        sum_possible_games(&games, &standard_bag());
+    }
+
     #[test]
     fn add_possible_games(){
         const INPUT_GAMES : &[u8] =
@@ -136,23 +279,46 @@ mod tests{
              Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
              Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green".as_bytes();
 
-        const INPUT_DICE : DiceCount = DiceCount{
-            red: 12,
-            green: 13,
-            blue: 14
-        };
+        let input_dice = standard_bag();
 
-        let games = parse_games(INPUT_GAMES).unwrap();
-        let count = super::add_possible_games(&games, &INPUT_DICE);
+        let games = parse_games(INPUT_GAMES, &Colors::standard()).unwrap();
+        let count = sum_possible_games(&games, &input_dice);
         assert_eq!(count, 8);
     }
 
+    #[test]
+    fn test_generated(){
+        let input = generate(&mut Rng::new(2), 100);
+        let games = parse_games(input.as_bytes(), &Colors::standard()).unwrap();
+        assert_eq!(games.len(), 100);
+        assert_eq!(sum_possible_games(&games, &Colors::standard().bag(&[("red", 20), ("green", 20), ("blue", 20)]).unwrap()), (1..=100).sum::<u32>());
+    }
+
+    #[test]
+    fn test_normalize(){
+        let normalized = normalize(b"Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green\nGame 2: 0 blue").unwrap();
+        assert_eq!(normalized, "Game 1: 4 red, 3 blue; 1 red, 2 green, 6 blue; 2 green\nGame 2: 0 red\n");
+        assert_eq!(normalize(normalized.as_bytes()).unwrap(), normalized);
+    }
+
     #[test]
     fn test_power(){
         const INPUT : &str = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green";
-        let game = parse_game(INPUT).unwrap();
+        let game = parse_game(INPUT, 1, &Colors::standard()).unwrap();
         let power = calculate_power(&game);
         assert_eq!(power, 48);
     }
 
+    proptest!{
+        #[test]
+        fn test_parse_game_round_trip(
+            id in any::<u32>(),
+            records in prop::collection::vec(prop::collection::vec(0u32..30, 3), 1..6),
+        ){
+            let game = Game{ id, records: records.into_iter().map(DiceCount).collect() };
+            let colors = Colors::standard();
+            prop_assert_eq!(parse_game(&game.display(&colors).to_string(), 1, &colors), Ok(game));
+        }
+    }
+
 }
\ No newline at end of file
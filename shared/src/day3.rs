@@ -1,174 +1,305 @@
-use std::{fs::File, io::{BufReader, BufRead}};
+use std::collections::HashMap;
 
-struct EngineSchematic{
-    rows: Vec<Vec<u8>>
+use itertools::Itertools;
+use thiserror::Error;
+
+use crate::{generate::Rng, visualize::{self, Frame}};
+
+#[derive(Debug)]
+pub struct EngineSchematic<'a>{
+    /// Rows borrowed from the input
+    rows: Vec<&'a [u8]>,
+    /// Every number in the schematic, in reading order
+    numbers: Vec<Number>,
 }
 
+/// The rows as they were read, the numbers are found in them again when parsing
+impl std::fmt::Display for EngineSchematic<'_>{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        for row in self.rows.iter(){
+            writeln!(f, "{}", String::from_utf8_lossy(row))?;
+        }
+        Ok(())
+    }
+}
+
+/// A number of the schematic, rows and columns count from 0
 #[derive(Clone, Copy, Eq, PartialEq, Debug)]
-struct Number{
-    value: u32,
-    row: usize,
-    start_column: usize,
-    end_column: usize
+pub struct Number{
+    pub value: u32,
+    pub row: usize,
+    pub start_column: usize,
+    /// Column just past the last digit
+    pub end_column: usize
 }
 
-fn read_schematic<R : std::io::BufRead>(reader: R) -> Result<EngineSchematic, std::io::Error>{
-    let rows : Result<Vec<Vec<u8>>, std::io::Error> = reader
-        .lines()
-        .map(|row| row.map(|row| row.as_bytes().to_vec()))
-        .collect();
+#[derive(Error, Debug)]
+pub enum SchematicError{
+    #[error("the number at line {line}, column {column} does not fit in 32 bits")]
+    NumberTooLarge{ line: usize, column: usize },
+}
 
-    rows.map(|rows| EngineSchematic{ rows })
+pub fn read_schematic(input: &[u8]) -> Result<EngineSchematic<'_>, SchematicError>{
+    let rows: Vec<&[u8]> = crate::input::lines(input).collect();
+
+    let numbers = find_numbers(&rows)?;
+    Ok(EngineSchematic{ rows, numbers })
 }
 
-fn commit_number(schematic: &EngineSchematic, numbers: &mut Vec<Number>, pending: &mut Option<Number>, current_x: usize){
-    if let Some(number) = pending.as_mut(){
+fn commit_number(rows: &[&[u8]], numbers: &mut Vec<Number>, pending: &mut Option<Number>, current_x: usize) -> Result<(), SchematicError>{
+    if let Some(mut number) = pending.take(){
         number.end_column = current_x;
-        number.value = std::str::from_utf8(&schematic.rows[number.row][number.start_column..number.end_column]).unwrap().parse::<u32>().unwrap();
-        numbers.push(number.clone());
-        *pending = None;
+        number.value = rows[number.row][number.start_column..number.end_column]
+            .iter()
+            .try_fold(0u32, |value, digit| value.checked_mul(10)?.checked_add((digit - b'0') as u32))
+            .ok_or(SchematicError::NumberTooLarge{ line: number.row + 1, column: number.start_column + 1 })?;
+        numbers.push(number);
     }
+    Ok(())
 }
 
-fn find_numbers(schematic: &EngineSchematic) -> Vec<Number>{
+fn find_numbers(rows: &[&[u8]]) -> Result<Vec<Number>, SchematicError>{
     let mut result = Vec::new();
 
-    for y in 0..schematic.rows.len(){
+    for (y, row) in rows.iter().enumerate(){
         let mut current_number :Option<Number> =  None;
 
-        for x in 0..schematic.rows[y].len(){
+        for (x, value) in row.iter().enumerate(){
             // Start new number range
-            if schematic.rows[y][x].is_ascii_digit() && current_number.is_none(){
+            if value.is_ascii_digit() && current_number.is_none(){
                 current_number = Some(Number { value: 0, row: y, start_column: x, end_column: x });
             }
-            if !schematic.rows[y][x].is_ascii_digit(){
-                commit_number(schematic, &mut result, &mut current_number, x);
+            if !value.is_ascii_digit(){
+                commit_number(rows, &mut result, &mut current_number, x)?;
             }
         }
 
-        commit_number(schematic, &mut result, &mut current_number, schematic.rows[y].len());
+        commit_number(rows, &mut result, &mut current_number, row.len())?;
     }
 
-    result
+    Ok(result)
 }
 
-fn is_symbol(schematic: &EngineSchematic, x: usize, y: usize) -> bool{
-    let value = schematic.rows[y][x];
-    !value.is_ascii_digit() && value != b'.'
+/// Characters that count as symbols next to part numbers
+#[derive(Clone, Debug)]
+pub struct SymbolSet{
+    symbols: [bool; 256],
 }
 
-fn test_adjacent_row(schematic: &EngineSchematic, number: &Number, y: usize) -> bool{
-    let adjacent_x_start = ((number.start_column as isize) - 1).max(0) as usize;
-    let adjacent_x_end = (number.end_column + 1).min(schematic.rows[y].len());
-    for x in adjacent_x_start..adjacent_x_end{
-        if is_symbol(schematic, x, y){
-            return true;
+impl SymbolSet{
+    /// Only the given characters are symbols
+    pub fn from_chars(chars: &[u8]) -> SymbolSet{
+        let mut symbols = [false; 256];
+        for char in chars{
+            symbols[*char as usize] = true;
         }
+        SymbolSet{ symbols }
     }
 
-    false
+    pub fn contains(&self, value: u8) -> bool{
+        self.symbols[value as usize]
+    }
 }
 
-fn is_part(schematic: &EngineSchematic, number: &Number) -> bool{
+impl Default for SymbolSet{
+    /// The puzzle's rule, anything that isn't a digit or a period
+    fn default() -> Self{
+        let mut symbols = [true; 256];
+        for char in (b'0'..=b'9').chain([b'.']){
+            symbols[char as usize] = false;
+        }
+        SymbolSet{ symbols }
+    }
+}
 
-    let mut any_symbol = false;
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct Symbol{
+    pub value: u8,
+    pub row: usize,
+    pub column: usize,
+}
 
-    // Test top of number
-    if number.row > 0{
-        any_symbol |= test_adjacent_row(schematic, number, number.row - 1);
-    }
+/// Numbers and symbols of a schematic together with which of them touch
+pub struct PartIndex{
+    numbers: Vec<Number>,
+    symbols: Vec<Symbol>,
+    /// Indices of the symbols around every number
+    number_symbols: Vec<Vec<usize>>,
+    /// Indices of the numbers around every symbol
+    symbol_numbers: Vec<Vec<usize>>,
+}
+
+impl PartIndex{
+    pub fn new(schematic: &EngineSchematic, symbol_set: &SymbolSet) -> PartIndex{
+        let numbers = schematic.numbers.clone();
+
+        let mut symbols = Vec::new();
+        let mut symbol_at = HashMap::new();
+        for (row, line) in schematic.rows.iter().enumerate(){
+            for (column, value) in line.iter().enumerate(){
+                if symbol_set.contains(*value){
+                    symbol_at.insert((column, row), symbols.len());
+                    symbols.push(Symbol{ value: *value, row, column });
+                }
+            }
+        }
+
+        // Every number looks at the cells around it once, which links both sides in a single pass
+        let mut number_symbols = vec![Vec::new(); numbers.len()];
+        let mut symbol_numbers = vec![Vec::new(); symbols.len()];
+        for (number_index, number) in numbers.iter().enumerate(){
+            let rows = number.row.saturating_sub(1)..=number.row + 1;
+            let columns = number.start_column.saturating_sub(1)..=number.end_column;
+            for (column, row) in columns.cartesian_product(rows){
+                if let Some(symbol_index) = symbol_at.get(&(column, row)){
+                    number_symbols[number_index].push(*symbol_index);
+                    symbol_numbers[*symbol_index].push(number_index);
+                }
+            }
+        }
 
-    // test left of number
-    if number.start_column > 0{
-        any_symbol |= is_symbol(schematic, number.start_column - 1, number.row);
+        PartIndex{ numbers, symbols, number_symbols, symbol_numbers }
     }
 
-    // test right of number
-    if number.end_column < schematic.rows[number.row].len(){
-        any_symbol |= is_symbol(schematic, number.end_column, number.row);
+    fn is_part(&self, number_index: usize) -> bool{
+        !self.number_symbols[number_index].is_empty()
     }
 
-    // Test row under number
-    if number.row < schematic.rows.len() - 1{
-        any_symbol |= test_adjacent_row(schematic, number, number.row + 1);
+    /// Numbers that touch at least one symbol
+    pub fn parts(&self) -> impl Iterator<Item = &Number>{
+        self.numbers.iter().enumerate().filter(|(index, _)| self.is_part(*index)).map(|(_, number)| number)
     }
 
-    any_symbol
+    /// Symbols with the given character that touch exactly count numbers, along with those numbers
+    pub fn gears(&self, value: u8, count: usize) -> impl Iterator<Item = (&Symbol, Vec<&Number>)>{
+        self.symbols
+            .iter()
+            .zip(self.symbol_numbers.iter())
+            .filter(move |(symbol, numbers)| symbol.value == value && numbers.len() == count)
+            .map(|(symbol, numbers)| (symbol, numbers.iter().map(|index| &self.numbers[*index]).collect()))
+    }
 
+    /// Every number with the symbols it touches, including numbers that touch none
+    pub fn report(&self) -> impl Iterator<Item = (&Number, Vec<&Symbol>)>{
+        self.numbers
+            .iter()
+            .zip(self.number_symbols.iter())
+            .map(|(number, symbols)| (number, symbols.iter().map(|index| &self.symbols[*index]).collect()))
+    }
 }
 
 fn find_part_numbers(schematic: &EngineSchematic) -> Vec<Number>{
-    find_numbers(schematic).into_iter().filter(|number| is_part(schematic, number)).collect()
+    let index = PartIndex::new(schematic, &SymbolSet::default());
+
+    // One frame per row, with the numbers checked so far marked as parts or not
+    for row in 0..schematic.rows.len(){
+        visualize::emit(|| schematic_frame(schematic, &index, row));
+    }
+
+    index.parts().copied().collect()
 }
 
-fn sum_parts(schematic: &EngineSchematic) -> u32{
+fn schematic_frame(schematic: &EngineSchematic, index: &PartIndex, row: usize) -> Frame{
+    let lines = schematic.rows.iter().map(|line| String::from_utf8_lossy(line).into_owned());
+    let mut frame = Frame::from_lines(format!("Checking row {} of {}", row + 1, schematic.rows.len()), lines);
+
+    for symbol in index.symbols.iter(){
+        frame.highlight(symbol.column, symbol.row, visualize::HIGHLIGHT);
+    }
+
+    for (number_index, number) in index.numbers.iter().enumerate().take_while(|(_, number)| number.row <= row){
+        let color = if index.is_part(number_index) { visualize::GOOD } else { visualize::BAD };
+        frame.highlight_span(number.start_column, number.row, number.end_column - number.start_column, color);
+    }
+
+    frame
+}
+
+pub fn sum_parts(schematic: &EngineSchematic) -> u32{
     find_part_numbers(schematic)
         .iter()
         .map(|number| number.value)
         .sum()
 }
 
-#[aoc_2023_markup::aoc_task(2023, 3, 1)]
-fn part1(input: &mut dyn BufRead) -> Result<u32, std::io::Error>{
+#[aoc_2023_markup::aoc_task(2023, 3, 1, parse = read_schematic, solve = sum_parts)]
+pub fn part1(input: &[u8]) -> Result<u32, SchematicError>{
     let input = read_schematic(input);
     input.map(|schematic| sum_parts(&schematic))
 }
 
+/// Reads a schematic and indexes its parts with a symbol set of its own
+pub fn read_schematic_with(input: &[u8], symbols: &SymbolSet) -> Result<PartIndex, SchematicError>{
+    Ok(PartIndex::new(&read_schematic(input)?, symbols))
+}
 
-fn is_adjacent(number: &Number, x: usize, y: usize) -> bool{
-    if number.row > 0 && y < number.row - 1{
-        return false;
-    }
-
-    if y > number.row + 1{
-        return false;
-    }
-
-    if number.start_column > 0 && x < number.start_column - 1{
-        return false;
-    }
-
-    if x > number.end_column{
-        return false;
-    }
-
-    if y == number.row && x >= number.start_column && x < number.end_column{
-        return false;
-    }
-
-    true
+/// Sum of the products of the numbers around every symbol with the given character that touches exactly count numbers
+pub fn sum_gear_ratios(index: &PartIndex, value: u8, count: usize) -> u32{
+    index.gears(value, count)
+        .map(|(_, numbers)| numbers.iter().map(|number| number.value).product::<u32>())
+        .sum()
 }
 
-fn find_sum_gear_ratios(schematic: &EngineSchematic) -> u32{
+pub fn find_sum_gear_ratios(schematic: &EngineSchematic) -> u32{
+    let index = PartIndex::new(schematic, &SymbolSet::default());
+    sum_gear_ratios(&index, b'*', 2)
+}
 
-    let part_numbers = find_part_numbers(schematic);
-    let mut sum = 0;
+#[aoc_2023_markup::aoc_task(2023, 3, 2, parse = read_schematic, solve = find_sum_gear_ratios)]
+pub fn part2(input: &[u8]) -> Result<u32, SchematicError>{
+    let input = read_schematic(input);
+    input.map(|schematic| find_sum_gear_ratios(&schematic))
+}
 
-    for y in 0..schematic.rows.len(){
-        for x in 0..schematic.rows[y].len(){
-            let value = schematic.rows[y][x];
-            if value == b'*'{
-                let adjacent: Vec<Number> = part_numbers.iter().filter(|number| is_adjacent(number, x, y)).copied().collect();
-                if adjacent.len() == 2{
-                    sum += adjacent[0].value * adjacent[1].value;
+/// Square schematic with numbers of up to three digits and symbols scattered over it
+pub fn generate(rng: &mut Rng, size: usize) -> String{
+    const SYMBOLS: &[u8] = b"*#+$/@=%-&";
+
+    let mut result = String::new();
+    for _ in 0..size{
+        let mut row = vec![b'.'; size];
+        let mut x = 0;
+        while x < size{
+            let length = rng.range(1..4);
+            if rng.chance(0.15) && x + length <= size{
+                row[x] = b'1' + rng.below(9) as u8;
+                for digit in row[x + 1..x + length].iter_mut(){
+                    *digit = b'0' + rng.below(10) as u8;
                 }
+                // Keep a gap so the next number does not join this one
+                x += length + 1;
+                continue;
             }
+
+            if rng.chance(0.08){
+                row[x] = *rng.choose(SYMBOLS);
+            }
+            x += 1;
         }
+        result.push_str(&String::from_utf8(row).unwrap());
+        result.push('\n');
     }
+    result
+}
 
-    sum
+/// Reads the schematic and indexes its parts
+pub fn fuzz(data: &[u8]){
+    if let Ok(schematic) = read_schematic(data){
+        let _ = PartIndex::new(&schematic, &SymbolSet::default());
+    }
 }
 
-#[aoc_2023_markup::aoc_task(2023, 3, 2)]
-fn part2(input: &mut dyn BufRead) -> Result<u32, std::io::Error>{
-    let input = read_schematic(input);
-    input.map(|schematic| find_sum_gear_ratios(&schematic))
+/// Rewrites the schematic with every row ending in a single newline
+pub fn normalize(data: &[u8]) -> Option<String>{
+    read_schematic(data).ok().map(|schematic| schematic.to_string())
 }
 
 #[cfg(test)]
 mod tests{
     use super::*;
     use indoc::indoc;
+    use proptest::prelude::*;
+    use crate::generate::Rng;
 
     #[test]
     fn test_sum_parts(){
@@ -191,6 +322,23 @@ mod tests{
         assert_eq!(sum, 4361);
     }
 
+    #[test]
+    fn test_part_frames(){
+        const INPUT : &[u8]= indoc!{"
+            467..114..
+            ...*......
+        "}.as_bytes();
+
+        let schematic = read_schematic(INPUT).unwrap();
+        let (parts, frames) = visualize::record(|| find_part_numbers(&schematic));
+        assert_eq!(parts.len(), 1);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1].to_text(), "Checking row 2 of 2\n467..114..\n...*......\n");
+        assert!(frames[0].highlights.contains(&(0, 0, visualize::GOOD)));
+        assert!(frames[0].highlights.contains(&(5, 0, visualize::BAD)));
+        assert!(frames[0].highlights.contains(&(3, 1, visualize::HIGHLIGHT)));
+    }
+
     #[test]
     fn test_find_numbers(){
         const INPUT : &[u8]= indoc!{"
@@ -200,8 +348,7 @@ mod tests{
         "}.as_bytes();
 
         let schematic = read_schematic(INPUT).unwrap();
-        let numbers = find_numbers(&schematic);
-        assert_eq!(numbers, [
+        assert_eq!(schematic.numbers, [
             Number{ row: 0, start_column: 0, end_column: 3, value: 467 },
             Number{ row: 0, start_column: 5, end_column: 8, value: 114 },
             Number{ row: 2, start_column: 2, end_column: 4, value: 35 },
@@ -209,6 +356,15 @@ mod tests{
         ]);
     }
 
+    #[test]
+    fn test_number_too_large(){
+        let schematic = read_schematic("..4294967295*".as_bytes()).unwrap();
+        assert_eq!(schematic.numbers[0].value, u32::MAX);
+
+        let error = read_schematic("...\n.4294967296".as_bytes()).unwrap_err();
+        assert!(matches!(error, SchematicError::NumberTooLarge{ line: 2, column: 2 }));
+    }
+
     #[test]
     fn test_find_sum_gear_ratios(){
         const INPUT : &[u8] = indoc! {"
@@ -229,4 +385,112 @@ mod tests{
         assert_eq!(sum, 467835);
     }
 
-}
\ No newline at end of file
+    const SAMPLE : &[u8] = indoc! {"
+        467..114..
+        ...*......
+        ..35..633.
+        ......#...
+        617*......
+        .....+.58.
+        ..592.....
+        ......755.
+        ...$.*....
+        .664.598..
+    "}.as_bytes();
+
+    #[test]
+    fn test_symbol_set(){
+        let schematic = read_schematic(SAMPLE).unwrap();
+        let index = PartIndex::new(&schematic, &SymbolSet::from_chars(b"#"));
+        assert_eq!(index.parts().map(|number| number.value).collect::<Vec<_>>(), [633]);
+
+        let index = PartIndex::new(&schematic, &SymbolSet::from_chars(b"*+"));
+        assert_eq!(index.parts().map(|number| number.value).sum::<u32>(), 467 + 35 + 617 + 592 + 755 + 598);
+
+        let index = read_schematic_with(SAMPLE, &SymbolSet::from_chars(b"$")).unwrap();
+        assert_eq!(index.parts().map(|number| number.value).collect::<Vec<_>>(), [664]);
+        assert_eq!(sum_gear_ratios(&index, b'$', 1), 664);
+    }
+
+    #[test]
+    fn test_gears(){
+        let schematic = read_schematic(SAMPLE).unwrap();
+        let index = PartIndex::new(&schematic, &SymbolSet::default());
+
+        let (symbol, numbers) = index.gears(b'*', 1).exactly_one().ok().unwrap();
+        assert_eq!(*symbol, Symbol{ value: b'*', row: 4, column: 3 });
+        assert_eq!(numbers.iter().map(|number| number.value).collect::<Vec<_>>(), [617]);
+
+        assert_eq!(sum_gear_ratios(&index, b'*', 2), 467835);
+        assert_eq!(sum_gear_ratios(&index, b'#', 1), 633);
+        assert_eq!(sum_gear_ratios(&index, b'$', 2), 0);
+    }
+
+    #[test]
+    fn test_report(){
+        const INPUT : &[u8] = indoc! {"
+            12.7
+            .$*.
+            ...9
+        "}.as_bytes();
+
+        let schematic = read_schematic(INPUT).unwrap();
+        let index = PartIndex::new(&schematic, &SymbolSet::default());
+        let report: Vec<(u32, Vec<u8>)> = index.report()
+            .map(|(number, symbols)| (number.value, symbols.iter().map(|symbol| symbol.value).collect()))
+            .collect();
+        assert_eq!(report, [
+            (12, vec![b'$', b'*']),
+            (7, vec![b'*']),
+            (9, vec![b'*']),
+        ]);
+    }
+
+    #[test]
+    fn test_generated(){
+        let input = generate(&mut Rng::new(3), 60);
+        let schematic = read_schematic(input.as_bytes()).unwrap();
+        let numbers = &schematic.numbers;
+
+        // Check the index against looking around every number and every star directly
+        let touches = |number: &Number, x: usize, y: usize| {
+            y + 1 >= number.row && y <= number.row + 1 && x + 1 >= number.start_column && x <= number.end_column
+        };
+        let symbols = SymbolSet::default();
+        let cells = || (0..60).cartesian_product(0..60);
+        let parts: u32 = numbers
+            .iter()
+            .filter(|number| cells().any(|(x, y)| symbols.contains(schematic.rows[y][x]) && touches(number, x, y)))
+            .map(|number| number.value)
+            .sum();
+        let gears: u32 = cells()
+            .filter(|(x, y)| schematic.rows[*y][*x] == b'*')
+            .map(|(x, y)| numbers.iter().filter(|number| touches(number, x, y)).map(|number| number.value).collect::<Vec<_>>())
+            .filter(|adjacent| adjacent.len() == 2)
+            .map(|adjacent| adjacent[0] * adjacent[1])
+            .sum();
+
+        assert!(parts > 0);
+        assert_eq!(sum_parts(&schematic), parts);
+        assert_eq!(find_sum_gear_ratios(&schematic), gears);
+    }
+
+    proptest!{
+        #[test]
+        // Digit runs stay under ten digits, so that every number fits in 32 bits
+        fn test_read_schematic_round_trip(rows in prop::collection::vec("[0-9]{0,9}([.#*]{1,3}[0-9]{0,9}){0,3}", 0..8)){
+            let input: String = rows.iter().map(|row| format!("{}\n", row)).collect();
+            let schematic = read_schematic(input.as_bytes()).unwrap();
+            let rendered = schematic.to_string();
+            prop_assert_eq!(&rendered, &input);
+            let reparsed = read_schematic(rendered.as_bytes()).unwrap();
+            prop_assert_eq!(&reparsed.rows, &schematic.rows);
+            prop_assert_eq!(&reparsed.numbers, &schematic.numbers);
+
+            // Every digit belongs to exactly one number
+            let digits = rows.iter().flat_map(|row| row.bytes()).filter(u8::is_ascii_digit).count();
+            prop_assert_eq!(schematic.numbers.iter().map(|number| number.end_column - number.start_column).sum::<usize>(), digits);
+        }
+    }
+
+}
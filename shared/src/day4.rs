@@ -1,110 +1,274 @@
-use std::io::BufRead;
-
 use lazy_static::lazy_static;
 use regex::Regex;
+use thiserror::Error;
 
-#[derive(Debug, PartialEq, Eq)]
-struct Card{
-    id: u32,
-    winning: Vec<u8>,
-    have: Vec<u8>
+use crate::{explain, generate::Rng};
+
+/// Numbers on the cards go up to this
+const MAX_NUMBER: u8 = 99;
+
+/// Set of numbers from 0 to MAX_NUMBER stored as bits
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct NumberSet(u128);
+
+impl NumberSet{
+    /// Adds the number, returns false when it was already in the set
+    fn insert(&mut self, number: u8) -> bool{
+        let bit = 1u128 << number;
+        let added = self.0 & bit == 0;
+        self.0 |= bit;
+        added
+    }
+
+    fn intersection_count(&self, other: &NumberSet) -> usize{
+        (self.0 & other.0).count_ones() as usize
+    }
+}
+
+impl FromIterator<u8> for NumberSet{
+    fn from_iter<T: IntoIterator<Item = u8>>(iter: T) -> Self{
+        let mut set = NumberSet::default();
+        for number in iter{
+            set.insert(number);
+        }
+        set
+    }
+}
+
+/// Numbers in increasing order, padded to two columns like on the cards
+impl std::fmt::Display for NumberSet{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        let numbers = (0..=MAX_NUMBER).filter(|number| self.0 & (1 << number) != 0);
+        for (index, number) in numbers.enumerate(){
+            write!(f, "{}{:>2}", if index > 0 { " " } else { "" }, number)?;
+        }
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
-struct ParseCardsError{
-    line: usize,
+#[derive(Debug, PartialEq, Eq)]
+pub struct Card{
+    id: u32,
+    winning: NumberSet,
+    have: NumberSet,
 }
 
-impl std::fmt::Display for ParseCardsError{
+impl std::fmt::Display for Card{
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
-        write!(f, "failed to parse card on line {}", self.line)
+        write!(f, "Card {:>3}: {} | {}", self.id, self.winning, self.have)
     }
-} 
+}
 
-fn parse_number_list(list: &str) -> Option<Vec<u8>>{
-    list.split_ascii_whitespace().map(|number| number.parse::<u8>().ok()).collect()
+/// Errors carry the line they were found on, counting from 1
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ParseCardsError{
+    #[error("line {line} is not valid UTF-8")]
+    NotUtf8{ line: usize },
+    #[error("line {line}: expected \"Card <id>: <winning numbers> | <numbers you have>\"")]
+    Format{ line: usize },
+    #[error("line {line}: {number} is not a number from 0 to {MAX_NUMBER}")]
+    OutOfRange{ line: usize, number: String },
+    #[error("line {line}: {number} appears more than once in the {list} numbers")]
+    Duplicate{ line: usize, number: u8, list: &'static str },
 }
 
-fn parse_card(line: &str) -> Option<Card>{
+fn parse_number_list(list: &str, line: usize, name: &'static str) -> Result<NumberSet, ParseCardsError>{
+    let mut set = NumberSet::default();
+    for number in list.split_ascii_whitespace(){
+        let value = number
+            .parse::<u8>()
+            .ok()
+            .filter(|value| *value <= MAX_NUMBER)
+            .ok_or_else(|| ParseCardsError::OutOfRange{ line, number: number.into() })?;
+        if !set.insert(value){
+            return Err(ParseCardsError::Duplicate{ line, number: value, list: name });
+        }
+    }
+    Ok(set)
+}
+
+fn parse_card(input: &str, line: usize) -> Result<Card, ParseCardsError>{
     lazy_static!{
-        static ref RE: Regex = Regex::new(r"Card +(\d+): (.*) \| (.*)").unwrap();
+        static ref RE: Regex = Regex::new(r"^Card +(\d+): (.*) \| (.*)$").unwrap();
     }
 
-    let captures = RE.captures(line)?;
-    let id = captures[1].parse::<u32>().ok()?;
-    let winning = parse_number_list(&captures[2])?;
-    let have = parse_number_list(&captures[3])?;
-    Some(Card{
+    let captures = RE.captures(input).ok_or(ParseCardsError::Format{ line })?;
+    let id = captures[1].parse::<u32>().map_err(|_| ParseCardsError::Format{ line })?;
+    let winning = parse_number_list(&captures[2], line, "winning")?;
+    let have = parse_number_list(&captures[3], line, "held")?;
+    Ok(Card{
         id,
         winning,
         have
     })
 }
 
-fn parse_cards<R: std::io::BufRead>(input: R) -> Result<Vec<Card>, ParseCardsError>{
-   input 
-        .lines()
+pub fn parse_cards(input: &[u8]) -> Result<Vec<Card>, ParseCardsError>{
+    crate::input::lines(input)
         .enumerate()
-        .map(|(line_index, line)| line.ok().and_then(|line| parse_card(&line)).ok_or(ParseCardsError{ line: line_index }))
+        .map(|(line_index, line)|{
+            let line = std::str::from_utf8(line).map_err(|_| ParseCardsError::NotUtf8{ line: line_index + 1 })?;
+            parse_card(line, line_index + 1)
+        })
         .collect()
 }
 
 fn card_matches(card: &Card) -> usize{
-    card.have.iter().filter(|value| card.winning.contains(&value)).count()
+    card.winning.intersection_count(&card.have)
 }
 
-fn card_score(card: &Card) -> u32{
+/// A card can match every number from 0 to MAX_NUMBER, so its score needs 128 bits
+pub fn card_score(card: &Card) -> u128{
     let count = card_matches(card);
     if count > 0{
-        1 << (count as u32 - 1)
+        1 << (count - 1)
     }
     else{
         0
     }
 }
 
-#[aoc_2023_markup::aoc_task(2023, 4, 1)]
-fn calculate_total_score(input: &mut dyn BufRead) -> Result<u32, ParseCardsError>{
-    parse_cards(input)
-        .map(|cards| cards.iter().map(|card| card_score(&card)).sum())
+/// What happened to a single card once every copy was scratched
+#[derive(Debug, PartialEq, Eq)]
+struct CardReport{
+    id: u32,
+    matches: usize,
+    points: u128,
+    /// Instances of the card held in the end, the original included
+    copies: usize,
+    /// Cards whose matches won copies of this one
+    granted_by: Vec<u32>,
 }
 
-#[aoc_2023_markup::aoc_task(2023, 4, 2)]
-fn count_scratchcards(input: &mut dyn BufRead) -> Result<usize, ParseCardsError>{
-    let cards = parse_cards(input)?;
-    let mut card_counts : Vec<usize> = vec![1; cards.len()];
+impl std::fmt::Display for CardReport{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        write!(f, "Card {}: matches {}, points {}, copies {}", self.id, self.matches, self.points, self.copies)?;
+        if !self.granted_by.is_empty(){
+            write!(f, ", won from cards {}", self.granted_by.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", "))?;
+        }
+        Ok(())
+    }
+}
 
-    for card_index in 0..cards.len(){
-        let match_count = card_matches(&cards[card_index]);
+/// Scratches every card, with each matching card winning copies of the cards after it
+fn simulate(cards: &[Card]) -> Vec<CardReport>{
+    let mut reports: Vec<CardReport> = cards
+        .iter()
+        .map(|card| CardReport{
+            id: card.id,
+            matches: card_matches(card),
+            points: card_score(card),
+            copies: 1,
+            granted_by: Vec::new(),
+        })
+        .collect();
 
+    for card_index in 0..reports.len(){
         let begin_prize = card_index + 1;
-        let end_prize = (begin_prize + match_count).min(cards.len());
+        let end_prize = (begin_prize + reports[card_index].matches).min(reports.len());
 
-        for i in begin_prize..end_prize{
-            card_counts[i] += card_counts[card_index];
+        let (id, copies) = (reports[card_index].id, reports[card_index].copies);
+        for report in reports[begin_prize..end_prize].iter_mut(){
+            report.copies += copies;
+            report.granted_by.push(id);
         }
     }
 
-    Ok(card_counts.iter().copied().sum::<usize>())
+    reports
+}
+
+pub fn total_score(cards: &[Card]) -> u128{
+    for card in cards.iter(){
+        explain::line(|| format!("Card {}: matches {}, points {}", card.id, card_matches(card), card_score(card)));
+    }
+    cards.iter().map(card_score).sum()
+}
+
+#[aoc_2023_markup::aoc_task(2023, 4, 1, parse = parse_cards, solve = total_score)]
+pub fn calculate_total_score(input: &[u8]) -> Result<u128, ParseCardsError>{
+    Ok(total_score(&parse_cards(input)?))
 }
 
+/// Cards held once every copy was scratched, the originals included
+pub fn total_scratchcards(cards: &[Card]) -> usize{
+    let reports = simulate(cards);
+    for report in reports.iter(){
+        explain::line(|| report.to_string());
+    }
+
+    reports.iter().map(|report| report.copies).sum::<usize>()
+}
+
+#[aoc_2023_markup::aoc_task(2023, 4, 2, parse = parse_cards, solve = total_scratchcards)]
+pub fn count_scratchcards(input: &[u8]) -> Result<usize, ParseCardsError>{
+    Ok(total_scratchcards(&parse_cards(input)?))
+}
+
+
+/// Cards with ten winning numbers and 25 held numbers.
+/// Most cards match nothing so the number of copies stays far from overflowing, like in the real inputs.
+pub fn generate(rng: &mut Rng, size: usize) -> String{
+    let mut result = String::new();
+    for id in 1..=size{
+        let mut numbers: Vec<u8> = (1..=MAX_NUMBER).collect();
+        rng.shuffle(&mut numbers);
+
+        // Cards never win copies past the end of the table
+        let matches = if rng.chance(0.7) { 0 } else { rng.range(1..6) }.min(size - id);
+        let winning = &numbers[..10];
+        let mut have: Vec<u8> = winning[..matches].iter().chain(numbers[10..35 - matches].iter()).copied().collect();
+        rng.shuffle(&mut have);
+
+        let format = |list: &[u8]| list.iter().map(|number| format!("{:>2}", number)).collect::<Vec<_>>().join(" ");
+        result.push_str(&format!("Card {:>3}: {} | {}\n", id, format(winning), format(&have)));
+    }
+    result
+}
+
+pub fn fuzz(data: &[u8]){
+    let _ = parse_cards(data);
+}
+
+/// Rewrites the cards with their numbers sorted
+pub fn normalize(data: &[u8]) -> Option<String>{
+    let cards = parse_cards(data).ok()?;
+    Some(cards.iter().map(|card| format!("{}\n", card)).collect())
+}
 
 #[cfg(test)]
 mod tests{
-    use std::io::Cursor;
-
     use indoc::indoc;
+    use proptest::prelude::*;
+    use crate::generate::Rng;
     use super::*;
 
     #[test]
     fn test_parse_card(){
         const INPUT : &str = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53";
-        let card = parse_card(INPUT).unwrap();
+        let card = parse_card(INPUT, 1).unwrap();
         assert_eq!(card, Card{
             id: 1,
-            winning: vec![41, 48, 83, 86, 17],
-            have: vec![83, 86, 6, 31, 17, 9, 48, 53]
+            winning: NumberSet::from_iter([41, 48, 83, 86, 17]),
+            have: NumberSet::from_iter([83, 86, 6, 31, 17, 9, 48, 53])
         });
+        assert_eq!(card.to_string(), "Card   1: 17 41 48 83 86 |  6  9 17 31 48 53 83 86");
+    }
+
+    #[test]
+    fn test_parse_errors(){
+        const INPUT : &[u8] = indoc!{"
+            Card 1: 41 48 | 83 86
+            Card 2: 13 32 | 61 100
+        "}.as_bytes();
+        assert_eq!(parse_cards(INPUT), Err(ParseCardsError::OutOfRange{ line: 2, number: "100".into() }));
+        assert_eq!(parse_card("Card 3: 1 2 1 | 4", 3), Err(ParseCardsError::Duplicate{ line: 3, number: 1, list: "winning" }));
+        assert_eq!(parse_card("Card 3: 1 2 | 4 4", 3), Err(ParseCardsError::Duplicate{ line: 3, number: 4, list: "held" }));
+        assert_eq!(parse_card("Card 3: 1 2 | -4", 3), Err(ParseCardsError::OutOfRange{ line: 3, number: "-4".into() }));
+        assert_eq!(parse_card("Card 3: 1 2 4", 3), Err(ParseCardsError::Format{ line: 3 }));
+        assert_eq!(
+            ParseCardsError::Duplicate{ line: 3, number: 1, list: "winning" }.to_string(),
+            "line 3: 1 appears more than once in the winning numbers"
+        );
     }
 
     #[test]
@@ -118,10 +282,21 @@ mod tests{
             Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11
         "}.as_bytes();
 
-        let score = calculate_total_score(&mut Cursor::new(INPUT)).unwrap();
+        let score = calculate_total_score(INPUT).unwrap();
         assert_eq!(score, 13);
     }
 
+    #[test]
+    fn test_score_many_matches(){
+        let numbers = (1..=33).map(|number| number.to_string()).collect::<Vec<_>>().join(" ");
+        let card = parse_card(&format!("Card 1: {} | {}", numbers, numbers), 1).unwrap();
+        assert_eq!(card_score(&card), 1 << 32);
+
+        let numbers = (0..=MAX_NUMBER).map(|number| number.to_string()).collect::<Vec<_>>().join(" ");
+        let card = parse_card(&format!("Card 1: {} | {}", numbers, numbers), 1).unwrap();
+        assert_eq!(card_score(&card), 1 << 99);
+    }
+
     #[test]
     fn test_count_cards(){
         const INPUT : &[u8] = indoc!{"
@@ -133,9 +308,39 @@ mod tests{
             Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11
         "}.as_bytes();
 
-        let count = count_scratchcards(&mut Cursor::new(INPUT)).unwrap();
+        let count = count_scratchcards(INPUT).unwrap();
         assert_eq!(count, 30);
 
+        let reports = simulate(&parse_cards(INPUT).unwrap());
+        assert_eq!(reports[3], CardReport{ id: 4, matches: 1, points: 1, copies: 8, granted_by: vec![1, 2, 3] });
+        assert_eq!(reports[0].to_string(), "Card 1: matches 4, points 8, copies 1");
+        assert_eq!(reports[2].to_string(), "Card 3: matches 2, points 2, copies 4, won from cards 1, 2");
+
+        let (_, explanation) = explain::collect(|| count_scratchcards(INPUT));
+        assert_eq!(explanation.len(), 6);
+        assert_eq!(explanation[5], "Card 6: matches 0, points 0, copies 1");
+    }
+
+    #[test]
+    fn test_generated(){
+        let input = generate(&mut Rng::new(4), 300);
+        let cards = parse_cards(input.as_bytes()).unwrap();
+        let reports = simulate(&cards);
+        assert_eq!(reports.len(), 300);
+        assert!(reports.iter().all(|report| report.matches <= 5 && report.id as usize + report.matches <= 300));
+        assert_eq!(count_scratchcards(input.as_bytes()).unwrap(), reports.iter().map(|report| report.copies).sum::<usize>());
+    }
+
+    proptest!{
+        #[test]
+        fn test_parse_card_round_trip(
+            id in any::<u32>(),
+            winning in prop::collection::btree_set(0..=MAX_NUMBER, 0..10),
+            have in prop::collection::btree_set(0..=MAX_NUMBER, 0..25),
+        ){
+            let card = Card{ id, winning: winning.into_iter().collect(), have: have.into_iter().collect() };
+            prop_assert_eq!(parse_card(&card.to_string(), 1), Ok(card));
+        }
     }
 
 }
\ No newline at end of file
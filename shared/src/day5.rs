@@ -1,5 +1,7 @@
 use itertools::Itertools;
 
+use crate::generate::Rng;
+
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 struct MappingRange{
     source_start: usize,
@@ -29,7 +31,8 @@ impl MappingRange{
     }
 }
 
-struct Mapping{
+#[derive(PartialEq, Eq, Debug)]
+pub struct Mapping{
     ranges: Vec<MappingRange>
 }
 
@@ -41,7 +44,7 @@ impl Mapping{
     fn lookup_ranges(&self, ranges: &[std::ops::Range<usize>]) -> Vec<std::ops::Range<usize>>{
         
         let mut test = self.ranges.clone();
-        test.sort_by(|a, b| a.source_start.cmp(&b.source_start));
+        test.sort_by_key(|a| a.source_start);
 
         let mut result = Vec::new();
         for range in ranges.iter(){
@@ -69,21 +72,67 @@ impl Mapping{
     }
 }
 
-struct SeedMappings{
+impl std::fmt::Display for MappingRange{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        write!(f, "{} {} {}", self.destination_start, self.source_start, self.count)
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct SeedMappings{
     seeds: Vec<usize>,
     mappings: Vec<Mapping>
 }
 
-struct SeedRangeMappings{
+#[derive(PartialEq, Eq, Debug)]
+pub struct SeedRangeMappings{
     seed_ranges: Vec<std::ops::Range<usize>>,
     mappings: Vec<Mapping>
 }
 
-fn parse_mapping_line(line: &str) -> Option<MappingRange>{
-    let mut split = line.split_ascii_whitespace();
-    let destination_start = split.next()?.parse::<usize>().ok()?;
-    let source_start = split.next()?.parse::<usize>().ok()?;
-    let count = split.next()?.parse::<usize>().ok()?;
+/// Writes the maps in order, the parser ignores their names so the categories are only named for the reader
+fn write_mappings(f: &mut std::fmt::Formatter<'_>, mappings: &[Mapping]) -> std::fmt::Result{
+    let category = |index: usize| CATEGORIES.get(index).map_or_else(|| format!("category{}", index), |name| name.to_string());
+    for (index, mapping) in mappings.iter().enumerate(){
+        writeln!(f, "\n{}-to-{} map:", category(index), category(index + 1))?;
+        for range in mapping.ranges.iter(){
+            writeln!(f, "{}", range)?;
+        }
+    }
+    Ok(())
+}
+
+impl std::fmt::Display for SeedMappings{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        write!(f, "seeds:")?;
+        for seed in self.seeds.iter(){
+            write!(f, " {}", seed)?;
+        }
+        writeln!(f)?;
+        write_mappings(f, &self.mappings)
+    }
+}
+
+impl std::fmt::Display for SeedRangeMappings{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        write!(f, "seeds:")?;
+        for range in self.seed_ranges.iter(){
+            write!(f, " {} {}", range.start, range.len())?;
+        }
+        writeln!(f)?;
+        write_mappings(f, &self.mappings)
+    }
+}
+
+fn parse_mapping_line(line: &[u8]) -> Option<MappingRange>{
+    let mut split = crate::input::fields(line);
+    let destination_start = crate::input::parse::<usize>(split.next()?)?;
+    let source_start = crate::input::parse::<usize>(split.next()?)?;
+    let count = crate::input::parse::<usize>(split.next()?)?;
+
+    // Both ends have to be representable for the range to be looked up
+    source_start.checked_add(count)?;
+    destination_start.checked_add(count)?;
     Some(MappingRange{
         destination_start,
         source_start,
@@ -91,14 +140,12 @@ fn parse_mapping_line(line: &str) -> Option<MappingRange>{
     })
 }
 
-fn parse_mappings(lines: &mut impl Iterator<Item = std::io::Result<String>>) -> Option<Vec<Mapping>>{
+fn parse_mappings<'a>(lines: impl Iterator<Item = &'a [u8]>) -> Option<Vec<Mapping>>{
     let mut mappings = Vec::new();
     let mut current_mappings= Vec::new();
 
-    while let Some(line) = lines.next(){
-        let line = line.ok()?;
-
-        if line.contains(':'){
+    for line in lines{
+        if line.contains(&b':'){
             if !current_mappings.is_empty(){
                 mappings.push(Mapping{ ranges: current_mappings });
                 current_mappings = Vec::new();
@@ -106,7 +153,7 @@ fn parse_mappings(lines: &mut impl Iterator<Item = std::io::Result<String>>) ->
             continue;
         }
 
-        if let Some(mapping) = parse_mapping_line(&line){
+        if let Some(mapping) = parse_mapping_line(line){
             current_mappings.push(mapping);
         }
     }
@@ -118,43 +165,41 @@ fn parse_mappings(lines: &mut impl Iterator<Item = std::io::Result<String>>) ->
     Some(mappings)
 }
 
-fn parse_seed_mapping<R: std::io::BufRead>(input: R) -> Option<SeedMappings>{
-    let mut lines = input.lines();
-    let seeds = lines.next()?.ok()?.split(':').nth(1)?.split_ascii_whitespace().map(|number| number.parse::<usize>().ok()).collect::<Option<Vec<usize>>>()?;
-    let mappings = parse_mappings(&mut lines)?;
+/// The numbers after the colon of the first line
+fn parse_seed_line(line: &[u8]) -> Option<Vec<usize>>{
+    let colon = line.iter().position(|byte| *byte == b':')?;
+    crate::input::fields(&line[colon + 1..]).map(crate::input::parse::<usize>).collect()
+}
+
+pub fn parse_seed_mapping(input: &[u8]) -> Option<SeedMappings>{
+    let mut lines = crate::input::lines(input);
+    let seeds = parse_seed_line(lines.next()?)?;
+    let mappings = parse_mappings(lines)?;
     Some(SeedMappings{
         seeds,
         mappings
     })
 }
 
-fn parse_seed_range_mappings<R: std::io::BufRead>(input: R) -> Option<SeedRangeMappings>{
-    let mut lines = input.lines();
-    let first_line = lines.next()?.ok()?;
-    let seed_ranges_string = first_line.split(':').nth(1)?;
-    let seed_ranges = seed_ranges_string
-        .split_ascii_whitespace()
+pub fn parse_seed_range_mappings(input: &[u8]) -> Option<SeedRangeMappings>{
+    let mut lines = crate::input::lines(input);
+    let seed_ranges = parse_seed_line(lines.next()?)?
         .chunks(2)
-        .into_iter()
-        .map(|range| {
-            let mut range_iter = range;
-            let start = range_iter.next()?.parse::<usize>().ok()?;
-            let count = range_iter.next()?.parse::<usize>().ok()?;
-            Some(start..(start + count))
+        .map(|range| match range{
+            [start, count] => Some(*start..start.checked_add(*count)?),
+            _ => None,
         })
         .collect::<Option<Vec<std::ops::Range<usize>>>>()?;
 
-    let mappings = parse_mappings(&mut lines)?;
+    let mappings = parse_mappings(lines)?;
     Some(SeedRangeMappings{
         seed_ranges,
         mappings
     })
 }
 
-#[aoc_2023_markup::aoc_task(2023, 5, 1)]
-fn lowest_location_with_seed<R: std::io::BufRead>(input: R) -> Option<usize>{
-    let mappings = parse_seed_mapping(input)?;
-
+/// Lowest location any of the seeds is planted at
+pub fn lowest_seed_location(mappings: &SeedMappings) -> Option<usize>{
     let locations = mappings.seeds.iter().map(|seed| {
         mappings.mappings.iter().fold(*seed, |a, b| b.lookup(a))
     });
@@ -162,17 +207,85 @@ fn lowest_location_with_seed<R: std::io::BufRead>(input: R) -> Option<usize>{
     locations.min()
 }
 
-#[aoc_2023_markup::aoc_task(2023, 5, 2)]
-fn lowest_location_with_seed_ranges<R: std::io::BufRead>(input: R) -> Option<usize>{
-    let mappings = parse_seed_range_mappings(input)?;
-    let locations : Vec<std::ops::Range<usize>> = mappings.mappings.iter().fold(mappings.seed_ranges, |a, b| b.lookup_ranges(&a));
+#[aoc_2023_markup::aoc_task(2023, 5, 1, parse = parse_seed_mapping, solve = lowest_seed_location)]
+pub fn lowest_location_with_seed(input: &[u8]) -> Option<usize>{
+    lowest_seed_location(&parse_seed_mapping(input)?)
+}
+
+/// Lowest location any seed of the ranges is planted at
+pub fn lowest_seed_range_location(mappings: &SeedRangeMappings) -> Option<usize>{
+    let locations : Vec<std::ops::Range<usize>> = mappings.mappings.iter().fold(mappings.seed_ranges.clone(), |a, b| b.lookup_ranges(&a));
     locations.iter().map(|range| range.start).min()
 }
 
+#[aoc_2023_markup::aoc_task(2023, 5, 2, parse = parse_seed_range_mappings, solve = lowest_seed_range_location)]
+pub fn lowest_location_with_seed_ranges(input: &[u8]) -> Option<usize>{
+    lowest_seed_range_location(&parse_seed_range_mappings(input)?)
+}
+
+/// Reference for part 2 that looks up every single seed, only usable for small ranges
+#[aoc_2023_markup::aoc_task(2023, 5, 2, "reference")]
+fn lowest_location_with_seed_ranges_reference(input: &[u8]) -> Option<usize>{
+    let mappings = parse_seed_range_mappings(input)?;
+    mappings.seed_ranges
+        .iter()
+        .flat_map(|range| range.clone())
+        .map(|seed| mappings.mappings.iter().fold(seed, |value, mapping| mapping.lookup(value)))
+        .min()
+}
+
+const CATEGORIES: [&str; 8] = ["seed", "soil", "fertilizer", "water", "light", "temperature", "humidity", "location"];
+
+/// Almanac with seed ranges inside 0..100·size and maps of about size ranges each.
+/// Every map cuts its values into blocks and moves them around, leaving some blocks in place.
+pub fn generate(rng: &mut Rng, size: usize) -> String{
+    let size = size.max(1);
+    let span = size * 100;
+
+    let seeds: Vec<String> = (0..rng.range(2..6))
+        .map(|_| format!("{} {}", rng.range(0..span), rng.range(1..span / 10 + 2)))
+        .collect();
+    let mut result = format!("seeds: {}\n", seeds.join(" "));
+
+    for (source, destination) in CATEGORIES.iter().tuple_windows(){
+        let mut cuts: Vec<usize> = (0..size - 1).map(|_| rng.range(1..span)).chain([0, span]).collect();
+        cuts.sort_unstable();
+        cuts.dedup();
+
+        let blocks: Vec<(usize, usize)> = cuts.iter().tuple_windows().map(|(start, end)| (*start, end - start)).collect();
+        let mut order: Vec<usize> = (0..blocks.len()).collect();
+        rng.shuffle(&mut order);
+
+        result.push_str(&format!("\n{}-to-{} map:\n", source, destination));
+        let mut destination_start = 0;
+        for index in order{
+            let (source_start, count) = blocks[index];
+            if rng.chance(0.8){
+                result.push_str(&format!("{} {} {}\n", destination_start, source_start, count));
+            }
+            destination_start += count;
+        }
+    }
+    result
+}
+
+/// Parses the almanac both with single seeds and with seed ranges
+pub fn fuzz(data: &[u8]){
+    let _ = parse_seed_mapping(data);
+    let _ = parse_seed_range_mappings(data);
+}
+
+/// Rewrites the almanac with the seeds on one line and the maps separated by blank lines
+pub fn normalize(data: &[u8]) -> Option<String>{
+    parse_seed_mapping(data).map(|almanac| almanac.to_string())
+}
+
 #[cfg(test)]
 mod tests{
     use super::*;
     use indoc::indoc;
+    use proptest::prelude::*;
+    use crate::generate::Rng;
 
     const SAMPLE_INPUT: &[u8] = indoc!{"
         seeds: 79 14 55 13
@@ -212,17 +325,17 @@ mod tests{
 
     #[test]
     fn test_mapping_lookup(){
-        let MAPPING = Mapping{
+        let mapping = Mapping{
             ranges: vec![
                 MappingRange{ source_start: 98, destination_start: 50, count: 2 },
                 MappingRange{ source_start: 50, destination_start: 52, count: 48 },
             ]
         };
 
-        assert_eq!(MAPPING.lookup(98), 50);
-        assert_eq!(MAPPING.lookup(99), 51);
-        assert_eq!(MAPPING.lookup(53), 55);
-        assert_eq!(MAPPING.lookup(10), 10);
+        assert_eq!(mapping.lookup(98), 50);
+        assert_eq!(mapping.lookup(99), 51);
+        assert_eq!(mapping.lookup(53), 55);
+        assert_eq!(mapping.lookup(10), 10);
     }
 
     #[test]
@@ -243,6 +356,7 @@ mod tests{
             MappingRange{ destination_start: 50, source_start: 98, count: 2},
             MappingRange{ destination_start: 52, source_start: 50, count: 48},
         ]);
+        assert_eq!(mappings.to_string().as_bytes(), SAMPLE_INPUT);
     }
 
     #[test]
@@ -255,5 +369,44 @@ mod tests{
     fn test_lowest_location_with_seed_ranges(){
         let lowest_location = lowest_location_with_seed_ranges(SAMPLE_INPUT).unwrap();
         assert_eq!(lowest_location, 46);
+        assert_eq!(lowest_location_with_seed_ranges_reference(SAMPLE_INPUT), Some(46));
+    }
+
+    #[test]
+    fn test_generated(){
+        for seed in 0..5{
+            let input = generate(&mut Rng::new(seed), 10);
+            assert_eq!(lowest_location_with_seed_ranges(input.as_bytes()), lowest_location_with_seed_ranges_reference(input.as_bytes()));
+            assert!(lowest_location_with_seed(input.as_bytes()).is_some());
+        }
+    }
+
+    fn mapping_strategy() -> impl Strategy<Value = Vec<Mapping>>{
+        let range = (0usize..1_000_000, 0usize..1_000_000, 1usize..10_000)
+            .prop_map(|(destination_start, source_start, count)| MappingRange{ destination_start, source_start, count });
+        prop::collection::vec(prop::collection::vec(range, 1..5).prop_map(|ranges| Mapping{ ranges }), 1..8)
     }
+
+    proptest!{
+        #[test]
+        fn test_parse_seed_mapping_round_trip(seeds in prop::collection::vec(0usize..1_000_000, 1..6), mappings in mapping_strategy()){
+            let almanac = SeedMappings{ seeds, mappings };
+            prop_assert_eq!(parse_seed_mapping(almanac.to_string().as_bytes()), Some(almanac));
+        }
+
+        #[test]
+        fn test_parse_seed_range_mappings_round_trip(ranges in prop::collection::vec((0usize..1_000_000, 1usize..1000), 1..6), mappings in mapping_strategy()){
+            let almanac = SeedRangeMappings{ seed_ranges: ranges.iter().map(|(start, count)| *start..start + count).collect(), mappings };
+            prop_assert_eq!(parse_seed_range_mappings(almanac.to_string().as_bytes()), Some(almanac));
+        }
+    }
+
+    #[test]
+    fn test_parse_overflow(){
+        let seeds = format!("seeds: {} 1", usize::MAX);
+        assert!(parse_seed_range_mappings(seeds.as_bytes()).is_none());
+        assert!(parse_mapping_line(format!("0 {} 2", usize::MAX - 1).as_bytes()).is_none());
+        assert!(parse_mapping_line(format!("{} 0 2", usize::MAX - 1).as_bytes()).is_none());
+    }
+
 }
\ No newline at end of file
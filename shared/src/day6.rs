@@ -1,15 +1,42 @@
+use crate::generate::Rng;
+
 
 #[derive(Debug, PartialEq, Eq)]
-struct Entry{
-    time: usize,
-    distance: usize,
+pub struct Entry<T>{
+    time: T,
+    distance: T,
+}
+
+/// Races side by side in right-aligned columns, like the puzzle input
+struct Table<'a, T>(&'a [Entry<T>]);
+
+impl<T: std::fmt::Display> std::fmt::Display for Table<'_, T>{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        let columns: Vec<(String, String)> = self.0.iter().map(|entry| (entry.time.to_string(), entry.distance.to_string())).collect();
+        for (name, row) in [("Time:", 0), ("Distance:", 1)]{
+            write!(f, "{:<9}", name)?;
+            for (time, distance) in columns.iter(){
+                let width = time.len().max(distance.len());
+                write!(f, "  {:>width$}", if row == 0 { time } else { distance })?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
 }
 
-fn parse_row(input: &str) -> Option<Vec<usize>>{
-    input.split_ascii_whitespace().skip(1).map(|item| item.parse::<usize>().ok()).collect()
+/// A single race, as part 2 reads the table
+impl<T: std::fmt::Display> std::fmt::Display for Entry<T>{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        Table(std::slice::from_ref(self)).fmt(f)
+    }
+}
+
+fn parse_row(input: &str) -> Option<Vec<u64>>{
+    input.split_ascii_whitespace().skip(1).map(|item| item.parse::<u64>().ok()).collect()
 }
 
-fn parse_table<R: std::io::BufRead>(input: R) -> Option<Vec<Entry>>{
+pub fn parse_table<R: std::io::BufRead>(input: R) -> Option<Vec<Entry<u64>>>{
     let mut lines = input.lines();
     let time_line = lines.next()?.ok()?;
     let distance_line = lines.next()?.ok()?;
@@ -32,12 +59,13 @@ fn parse_table<R: std::io::BufRead>(input: R) -> Option<Vec<Entry>>{
     Some(result)
 }
 
-fn parse_row_no_kerning(input: &str) -> Option<usize>{
+fn parse_row_no_kerning(input: &str) -> Option<u128>{
     let number : String = input.split(':').nth(1)?.chars().filter(|char| !char.is_whitespace()).collect();
-    number.parse::<usize>().ok()
+    number.parse::<u128>().ok()
 }
 
-fn parse_table_no_kerning<R: std::io::BufRead>(input: R) -> Option<Entry>{
+/// Parses the table as a single race, the concatenated numbers can exceed 64 bits
+pub fn parse_table_no_kerning<R: std::io::BufRead>(input: R) -> Option<Entry<u128>>{
     let mut lines = input.lines();
     let time_line = lines.next()?.ok()?;
     let distance_line = lines.next()?.ok()?;
@@ -46,33 +74,74 @@ fn parse_table_no_kerning<R: std::io::BufRead>(input: R) -> Option<Entry>{
     Some(Entry { time, distance})
 }
 
-fn num_beating(entry: &Entry) -> usize{
-    let time = entry.time as f64;
-    let distance = entry.distance as f64;
-    let b = ((time * time) - 4.0 * distance).sqrt();
-    let a = (-time + b) / -2.0;
-    let b = (-time - b) / -2.0;
-    let one = (a + 1.0).floor();
-    let two = b.ceil();
-    (two - one).floor() as usize
+/// Counts the hold times that beat the record: hold * (time - hold) > distance.
+/// Exact for any u64 or u128 input, None when time² does not fit in 128 bits.
+pub fn num_beating<T: Copy + Into<u128>>(entry: &Entry<T>) -> Option<u128>{
+    crate::math::count_quadratic_below_zero(entry.time.into(), entry.distance.into())
+}
+
+/// Reference for num_beating that tries every hold time, only usable for short races
+fn num_beating_reference<T: Copy + Into<u128>>(entry: &Entry<T>) -> u128{
+    let (time, distance) = (entry.time.into(), entry.distance.into());
+    (0..=time).filter(|hold| hold * (time - hold) > distance).count() as u128
 }
 
 #[aoc_2023_markup::aoc_task(2023, 6, 1)]
-fn multiply_ways_to_win<R: std::io::BufRead>(input: R) -> Option<usize>{
+pub fn multiply_ways_to_win<R: std::io::BufRead>(input: R) -> Option<u128>{
     let table = parse_table(input)?;
-    table.iter().map(|entry| num_beating(entry)).reduce(|a, b| a * b)
+    table.iter().map(num_beating).reduce(|a, b| a?.checked_mul(b?))?
 }
 
 #[aoc_2023_markup::aoc_task(2023, 6, 2)]
-fn ways_to_win_no_kerning<R: std::io::BufRead>(input: R) -> Option<usize>{
+pub fn ways_to_win_no_kerning<R: std::io::BufRead>(input: R) -> Option<u128>{
     let table = parse_table_no_kerning(input)?;
-    Some(num_beating(&table))
+    num_beating(&table)
+}
+
+#[aoc_2023_markup::aoc_task(2023, 6, 1, "reference")]
+fn multiply_ways_to_win_reference<R: std::io::BufRead>(input: R) -> Option<u128>{
+    let table = parse_table(input)?;
+    let mut counts = table.iter().map(num_beating_reference);
+    let first = counts.next()?;
+    counts.try_fold(first, |product, count| product.checked_mul(count))
+}
+
+#[aoc_2023_markup::aoc_task(2023, 6, 2, "reference")]
+fn ways_to_win_no_kerning_reference<R: std::io::BufRead>(input: R) -> Option<u128>{
+    let table = parse_table_no_kerning(input)?;
+    Some(num_beating_reference(&table))
+}
+
+/// Table of races that can all be won. The size is the number of races,
+/// capped at six so the joined race of part 2 still fits in 128 bits.
+pub fn generate(rng: &mut Rng, size: usize) -> String{
+    let entries: Vec<Entry<u64>> = (0..size.clamp(1, 6))
+        .map(|_|{
+            let time = rng.range(10..1000) as u64;
+            let best = (time / 2) * (time - time / 2);
+            Entry{ time, distance: rng.below(best) }
+        })
+        .collect();
+    Table(&entries).to_string()
+}
+
+/// Parses the table both as separate races and as a single one
+pub fn fuzz(data: &[u8]){
+    let _ = parse_table(data);
+    let _ = parse_table_no_kerning(data);
+}
+
+/// Rewrites the table with every column as narrow as its numbers allow
+pub fn normalize(data: &[u8]) -> Option<String>{
+    parse_table(data).map(|table| Table(&table).to_string())
 }
 
 #[cfg(test)]
 mod tests{
     use super::*;
     use indoc::indoc;
+    use proptest::prelude::*;
+    use crate::generate::Rng;
 
     const INPUT : &[u8] = indoc! {"
         Time:      7  15   30
@@ -88,6 +157,7 @@ mod tests{
             Entry{ time: 15, distance: 40},
             Entry{ time: 30, distance: 200 },
         ]);
+        assert_eq!(Table(&table).to_string().as_bytes(), INPUT);
     }
 
     #[test]
@@ -97,25 +167,113 @@ mod tests{
             time: 71530,
             distance: 940200
         });
+        assert_eq!(table.to_string(), "Time:       71530\nDistance:  940200\n");
     }
 
     #[test]
     fn test_num_beating(){
         let table = parse_table(INPUT).unwrap();
-        let test : Vec<usize> = table.iter().map(|entry| num_beating(entry)).collect();
+        let test : Vec<u128> = table.iter().map(num_beating).collect::<Option<_>>().unwrap();
         assert_eq!(test, [ 4, 8, 9 ]);
     }
 
+    #[test]
+    fn test_num_beating_exact_roots(){
+        // Holding 3 or 7 exactly ties the record, which does not count as beating it
+        assert_eq!(num_beating(&Entry{ time: 10u64, distance: 21 }), Some(3));
+        assert_eq!(num_beating(&Entry{ time: 10u64, distance: 24 }), Some(1));
+        assert_eq!(num_beating(&Entry{ time: 10u64, distance: 25 }), Some(0));
+        assert_eq!(num_beating(&Entry{ time: 0u64, distance: 0 }), Some(0));
+        assert_eq!(num_beating(&Entry{ time: 1u64, distance: 0 }), Some(0));
+        assert_eq!(num_beating(&Entry{ time: 2u64, distance: 0 }), Some(1));
+    }
+
+    #[test]
+    fn test_num_beating_large(){
+        // Beyond f64 precision: the roots of x * (t - x) = d are exactly 2^20 and t - 2^20
+        let time = 1u128 << 62;
+        let hold = 1u128 << 20;
+        let distance = hold * (time - hold);
+        assert_eq!(num_beating(&Entry{ time, distance }), Some(time - 2 * hold - 1));
+        assert_eq!(num_beating(&Entry{ time, distance: distance - 1 }), Some(time - 2 * hold + 1));
+
+        assert_eq!(num_beating(&Entry{ time: u64::MAX, distance: 0 }), Some(u64::MAX as u128 - 1));
+        assert_eq!(num_beating(&Entry{ time: u128::MAX, distance: 0 }), None);
+    }
+
+    proptest!{
+        #[test]
+        fn test_num_beating_matches_brute_force(time in 0u64..2000, distance in 0u64..1_000_000){
+            let entry = Entry{ time, distance };
+            prop_assert_eq!(num_beating(&entry), Some(num_beating_reference(&entry)));
+        }
+
+        #[test]
+        fn test_num_beating_matches_brute_force_near_roots(time in 0u64..2000, hold_fraction in 0.0f64..1.0, offset in -2i64..=2){
+            // Pick records close to an achievable distance so the roots land on or near integers
+            let hold = (time as f64 * hold_fraction) as u64;
+            let distance = (hold * (time - hold)).saturating_add_signed(offset);
+            let entry = Entry{ time, distance };
+            prop_assert_eq!(num_beating(&entry), Some(num_beating_reference(&entry)));
+        }
+
+        #[test]
+        fn test_num_beating_boundaries_u128(time in any::<u64>(), distance in any::<u128>()){
+            // Too large to brute force, check that the counted range is bounded by losing hold times instead
+            let time = time as u128;
+            let distance = distance % (time * time / 4 + 1);
+            let count = num_beating(&Entry{ time, distance }).unwrap();
+            let beats = |hold: u128| hold * (time - hold) > distance;
+            if count == 0{
+                prop_assert!(!beats(time / 2));
+            } else{
+                let first = (time + 1 - count) / 2;
+                let last = first + count - 1;
+                prop_assert!(beats(first) && beats(last));
+                prop_assert!(first == 0 || !beats(first - 1));
+                prop_assert!(last == time || !beats(last + 1));
+            }
+        }
+    }
+
     #[test]
     fn test_multiply_ways_to_win(){
         let result = multiply_ways_to_win(INPUT).unwrap();
         assert_eq!(result, 288);
+        assert_eq!(multiply_ways_to_win_reference(INPUT), Some(288));
     }
 
     #[test]
     fn test_ways_to_win_no_kerning(){
         let ways_to_win = ways_to_win_no_kerning(INPUT).unwrap();
         assert_eq!(ways_to_win, 71503);
+        assert_eq!(ways_to_win_no_kerning_reference(INPUT), Some(71503));
+    }
+
+
+    #[test]
+    fn test_generated(){
+        let input = generate(&mut Rng::new(6), 6);
+        let table = parse_table(input.as_bytes()).unwrap();
+        assert_eq!(table.len(), 6);
+        for entry in table.iter(){
+            assert!(num_beating(entry).unwrap() > 0);
+            assert_eq!(num_beating(entry), Some(num_beating_reference(entry)));
+        }
+        assert!(ways_to_win_no_kerning(input.as_bytes()).unwrap() > 0);
+    }
+
+    proptest!{
+        #[test]
+        fn test_parse_table_round_trip(entries in prop::collection::vec((0u64..1_000_000, 0u64..1_000_000), 1..6)){
+            let entries: Vec<Entry<u64>> = entries.into_iter().map(|(time, distance)| Entry{ time, distance }).collect();
+            let rendered = Table(&entries).to_string();
+            prop_assert_eq!(parse_table(rendered.as_bytes()), Some(entries.iter().map(|entry| Entry{ ..*entry }).collect()));
+
+            // Without kerning the digits of all races join into one race
+            let joined = |value: fn(&Entry<u64>) -> u64| entries.iter().map(|entry| value(entry).to_string()).collect::<String>().parse::<u128>().unwrap();
+            prop_assert_eq!(parse_table_no_kerning(rendered.as_bytes()), Some(Entry{ time: joined(|entry| entry.time), distance: joined(|entry| entry.distance) }));
+        }
     }
 
 }
\ No newline at end of file
@@ -1,254 +1,315 @@
-use std::{fmt::Write, cmp::Ordering};
+use std::collections::HashMap;
 
 use itertools::Itertools;
-use stackvector::StackVec;
-
-
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
-enum HandClassification{
-    HighCard,
-    OnePair,
-    TwoPair,
-    ThreeOfAKind,
-    FullHouse,
-    FourOfAKind,
-    FiveOfAKind,
-}
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
-#[repr(u8)]
-enum Card{
-    Two,
-    Three,
-    Four,
-    Five,
-    Six,
-    Seven,
-    Eight,
-    Nine,
-    T,
-    J,
-    Q,
-    K,
-    A
+use crate::generate::Rng;
+
+/// Strength of a hand of any size: how many cards each group of equal cards holds, largest first.
+/// Comparing the groups lexicographically gives the usual poker-like order.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+struct HandType(Vec<usize>);
+
+/// Which cards exist and how they rank, which of them is wild, and how many cards a hand holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleSet{
+    /// Cards from the lowest to the highest
+    ranking: Vec<char>,
+    /// Position of every card in the ranking, so ranking a card needs no search
+    ranks: HashMap<char, usize>,
+    wild: Option<char>,
+    hand_size: usize,
 }
 
-fn card_order<const USE_JOKERS: bool>(card: Card,) -> u8{
-    use Card::*;
-
-    match card{
-        J if USE_JOKERS => 1,
-        Two => 2,
-        Three => 3,
-        Four  => 4,
-        Five => 5,
-        Six => 6,
-        Seven => 7,
-        Eight => 8,
-        Nine => 9,
-        T => 10,
-        J => 11,
-        Q => 12,
-        K => 13,
-        A => 14,
+impl RuleSet{
+    /// None when cards repeat, the wild card is not ranked or hands would be empty
+    pub fn new(ranking: &str, wild: Option<char>, hand_size: usize) -> Option<RuleSet>{
+        let ranking: Vec<char> = ranking.chars().collect();
+        if !ranking.iter().all_unique() || wild.is_some_and(|wild| !ranking.contains(&wild)) || hand_size == 0{
+            return None;
+        }
+
+        let ranks = ranking.iter().enumerate().map(|(rank, card)| (*card, rank)).collect();
+        Some(RuleSet{ ranking, ranks, wild, hand_size })
+    }
+
+    pub fn standard() -> RuleSet{
+        RuleSet::new("23456789TJQKA", None, 5).unwrap()
+    }
+
+    /// Jacks become jokers, which are wild but rank below every other card
+    pub fn jokers() -> RuleSet{
+        RuleSet::new("J23456789TQKA", Some('J'), 5).unwrap()
     }
-}
 
-fn parse_card(char: char) -> Option<Card>{
-    use Card::*;
-    match char{
-        '2' => Some(Two),
-        '3' => Some(Three),
-        '4' => Some(Four),
-        '5' => Some(Five),
-        '6' => Some(Six),
-        '7' => Some(Seven),
-        '8' => Some(Eight),
-        '9' => Some(Nine),
-        'T' => Some(T),
-        'J' => Some(J),
-        'Q' => Some(Q),
-        'K' => Some(K),
-        'A' => Some(A),
-        _ => None
+    fn rank(&self, card: char) -> Option<usize>{
+        self.ranks.get(&card).copied()
     }
 }
 
 #[derive(PartialEq, Eq)]
-struct Hand([Card; 5]);
+pub struct Hand(Vec<char>);
 
 impl Hand{
-    fn try_from_str(str: &str) -> Option<Hand>{
-        let cards = str.chars().map(|c| parse_card(c)).collect::<Option<Vec<Card>>>()?;
-        Some(Hand(cards.try_into().ok()?))
+    fn try_from_str(str: &str, rules: &RuleSet) -> Option<Hand>{
+        let cards: Vec<char> = str.chars().collect();
+        if cards.len() != rules.hand_size || !cards.iter().all(|card| rules.rank(*card).is_some()){
+            return None;
+        }
+        Some(Hand(cards))
     }
 }
 
-impl std::fmt::Debug for Hand{
+impl std::fmt::Display for Hand{
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for card in self.0{
-            let char = match card{
-                Card::Two => '2',
-                Card::Three => '3',
-                Card::Four => '4',
-                Card::Five => '5',
-                Card::Six => '6',
-                Card::Seven => '7',
-                Card::Eight => '8',
-                Card::Nine => '9',
-                Card::T => 'T',
-                Card::J => 'J',
-                Card::Q => 'Q',
-                Card::K => 'K',
-                Card::A => 'A',
-            };
-            f.write_char(char)?;
-        }
+        f.write_str(&self.0.iter().collect::<String>())
+    }
+}
 
-        Ok(())
+impl std::fmt::Debug for Hand{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
     }
 }
 
-fn classify_hand<const USE_JOKERS: bool>(hand: &Hand) -> HandClassification{
+fn classify_hand(hand: &Hand, rules: &RuleSet) -> HandType{
     // Count unique cards in the hand
-    let mut joker_count = 0;
-    let mut counts = StackVec::<[(Card, u8); 5]>::new();
-    for card in hand.0{
-        if card == Card::J && USE_JOKERS{
-            joker_count = joker_count + 1;
-        } else if let Some(counter) = counts.iter_mut().find(|counter| counter.0 == card){
-            counter.1 = counter.1 + 1;
+    let mut wild_count = 0;
+    let mut counts: Vec<(char, usize)> = Vec::new();
+    for card in hand.0.iter(){
+        if Some(*card) == rules.wild{
+            wild_count += 1;
+        } else if let Some(counter) = counts.iter_mut().find(|counter| counter.0 == *card){
+            counter.1 += 1;
         } else{
-            counts.push((card, 1));
-        }
-    }
-
-    // Sort the unique cards by count
-    counts.sort_by(|a, b| b.1.cmp(&a.1));
-
-    let highest_count = counts.get(0).map(|pair| pair.1).unwrap_or(0);
-    let second_count = counts.get(1).map(|pair| pair.1).unwrap_or(0);
-
-    if joker_count + highest_count == 5{
-        return HandClassification::FiveOfAKind;
-    }
-
-    if joker_count + highest_count == 4{
-        return HandClassification::FourOfAKind;
-    }
-
-    if joker_count + highest_count == 3{
-        let jokers_left = joker_count - (3 - highest_count);
-        if jokers_left + second_count == 2{
-            return HandClassification::FullHouse;
-        }
-        else{
-            return HandClassification::ThreeOfAKind;
+            counts.push((*card, 1));
         }
     }
 
-    // Note: No joker checks for 2 pair, joker would always be consumed by the first pair to make a three of a kind
-    if highest_count == 2 && second_count == 2{
-        return HandClassification::TwoPair;
-    }
+    let mut groups: Vec<usize> = counts.into_iter().map(|count| count.1).sorted_by(|a, b| b.cmp(a)).collect();
 
-    if joker_count + highest_count == 2{
-        return HandClassification::OnePair;
+    // Wild cards always do best joining the largest group, which is the first thing hands are compared by
+    match groups.first_mut(){
+        Some(largest) => *largest += wild_count,
+        None if wild_count > 0 => groups.push(wild_count),
+        None => (),
     }
 
-    HandClassification::HighCard
+    HandType(groups)
 }
 
 #[derive(Debug, Eq, PartialEq)]
-struct Bid{
+pub struct Bid{
     hand: Hand,
     bid: usize,
 }
 
-fn parse_bid(line: &str) -> Option<Bid>{
-    let mut parts = line.split_ascii_whitespace(); 
-    let hand = Hand::try_from_str(parts.next()?)?;
+impl std::fmt::Display for Bid{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.hand, self.bid)
+    }
+}
+
+fn parse_bid(line: &str, rules: &RuleSet) -> Option<Bid>{
+    let mut parts = line.split_ascii_whitespace();
+    let hand = Hand::try_from_str(parts.next()?, rules)?;
     let bid = parts.next()?.parse::<usize>().ok()?;
     Some(Bid{
         hand, bid
     })
 }
 
-fn parse_bids<R: std::io::BufRead>(input: R) -> Option<Vec<Bid>>{
-    input.lines().map(|line| line.ok().and_then(|line| parse_bid(&line))).collect()
+pub fn parse_bids(input: &[u8], rules: &RuleSet) -> Option<Vec<Bid>>{
+    crate::input::lines(input).map(|line| parse_bid(std::str::from_utf8(line).ok()?, rules)).collect()
 }
 
-fn bid_compare_score<const USE_JOKERS: bool>(a: &(&Bid, HandClassification), b: &(&Bid, HandClassification)) -> std::cmp::Ordering{
-    let classification_order = a.1.cmp(&b.1);
-    if classification_order != Ordering::Equal{
-        return classification_order;
-    }
-    a.0.hand.0.map(|card| card_order::<USE_JOKERS>(card)).cmp(&b.0.hand.0.map(|card| card_order::<USE_JOKERS>(card)))
+/// What hands are ordered by: their type first, then their cards one by one
+fn bid_score(bid: &Bid, rules: &RuleSet) -> Option<(HandType, Vec<usize>)>{
+    let ranks = bid.hand.0.iter().map(|card| rules.rank(*card)).collect::<Option<Vec<usize>>>()?;
+    Some((classify_hand(&bid.hand, rules), ranks))
 }
 
-fn calculate_total_winnings<R: std::io::BufRead, const USE_JOKERS: bool>(input: R) -> Option<usize>{
-    let bids = parse_bids(input)?;
+pub fn calculate_total_winnings(input: &[u8], rules: &RuleSet) -> Option<usize>{
+    let mut bids = parse_bids(input, rules)?;
+
+    // Cards were checked against the ranking while parsing, so every bid has a score
+    bids.sort_by_cached_key(|bid| bid_score(bid, rules));
 
-    let classified_bids : Vec<(&Bid, HandClassification)>= bids.iter()
-        .map(|bid| (bid, classify_hand::<USE_JOKERS>(&bid.hand)))
-        .sorted_by(bid_compare_score::<USE_JOKERS>)
-        .collect();
-    
-    Some(classified_bids.iter().enumerate().map(|(rank, bid)| bid.0.bid * (rank + 1)).sum())
+    Some(bids.iter().enumerate().map(|(rank, bid)| bid.bid * (rank + 1)).sum())
 }
 
 #[aoc_2023_markup::aoc_task(2023, 7, 1)]
-fn part1<R: std::io::BufRead>(input: R) -> Option<usize>{
-    calculate_total_winnings::<_, false>(input)
+pub fn part1(input: &[u8]) -> Option<usize>{
+    calculate_total_winnings(input, &RuleSet::standard())
 }
 
 #[aoc_2023_markup::aoc_task(2023, 7, 2)]
-fn part2<R: std::io::BufRead>(input: R) -> Option<usize>{
-    calculate_total_winnings::<_,true>(input)
+pub fn part2(input: &[u8]) -> Option<usize>{
+    calculate_total_winnings(input, &RuleSet::jokers())
+}
+
+/// Distinct five card hands with bids of up to 1000
+pub fn generate(rng: &mut Rng, size: usize) -> String{
+    let rules = RuleSet::standard();
+    let mut hands = std::collections::HashSet::new();
+    let mut result = String::new();
+    // There are far more hands than anyone will generate, but do not spin forever when asked for more
+    while hands.len() < size.min(rules.ranking.len().pow(rules.hand_size as u32)){
+        let hand: String = (0..rules.hand_size).map(|_| *rng.choose(&rules.ranking)).collect();
+        if hands.insert(hand.clone()){
+            result.push_str(&format!("{} {}\n", hand, rng.range(1..1001)));
+        }
+    }
+    result
+}
+
+/// Parses the bids under both rule sets
+pub fn fuzz(data: &[u8]){
+    let _ = parse_bids(data, &RuleSet::standard());
+    let _ = parse_bids(data, &RuleSet::jokers());
+}
+
+/// Rewrites the bids with a single space between hand and bid
+pub fn normalize(data: &[u8]) -> Option<String>{
+    let bids = parse_bids(data, &RuleSet::standard())?;
+    Some(bids.iter().map(|bid| format!("{}\n", bid)).collect())
 }
 
 #[cfg(test)]
 mod tests{
     use indoc::indoc;
+    use proptest::prelude::*;
+    use crate::generate::Rng;
     use super::*;
 
+    // Naming only, hands are ranked by their groups
+    #[derive(Debug, PartialEq, Eq)]
+    enum HandClassification{
+        HighCard,
+        OnePair,
+        TwoPair,
+        ThreeOfAKind,
+        FullHouse,
+        FourOfAKind,
+        FiveOfAKind,
+    }
+
+    /// Name of the type of a five card hand, None for other hand sizes
+    fn classify(hand: &str, rules: &RuleSet) -> Option<HandClassification>{
+        use HandClassification::*;
+        match classify_hand(&Hand::try_from_str(hand, rules).unwrap(), rules).0.as_slice(){
+            [5] => Some(FiveOfAKind),
+            [4, 1] => Some(FourOfAKind),
+            [3, 2] => Some(FullHouse),
+            [3, 1, 1] => Some(ThreeOfAKind),
+            [2, 2, 1] => Some(TwoPair),
+            [2, 1, 1, 1] => Some(OnePair),
+            [1, 1, 1, 1, 1] => Some(HighCard),
+            _ => None,
+        }
+    }
 
     #[test]
     fn test_hand_from_str(){
-        use Card::*;
-        let hand = Hand::try_from_str("AAAAA").unwrap();
-        assert_eq!(hand, Hand([A, A, A, A, A]));
-        let hand = Hand::try_from_str("AA8AA").unwrap();
-        assert_eq!(hand, Hand([A, A, Eight, A, A]));
-        let hand = Hand::try_from_str("23332").unwrap();
-        assert_eq!(hand, Hand([Two, Three, Three, Three, Two]));
-        let hand = Hand::try_from_str("TTT98").unwrap();
-        assert_eq!(hand, Hand([T, T, T, Nine, Eight]));
+        let rules = RuleSet::standard();
+        let hand = Hand::try_from_str("AAAAA", &rules).unwrap();
+        assert_eq!(hand, Hand(vec!['A', 'A', 'A', 'A', 'A']));
+        let hand = Hand::try_from_str("AA8AA", &rules).unwrap();
+        assert_eq!(hand, Hand(vec!['A', 'A', '8', 'A', 'A']));
+        let hand = Hand::try_from_str("23332", &rules).unwrap();
+        assert_eq!(hand, Hand(vec!['2', '3', '3', '3', '2']));
+        let hand = Hand::try_from_str("TTT98", &rules).unwrap();
+        assert_eq!(hand, Hand(vec!['T', 'T', 'T', '9', '8']));
+
+        assert_eq!(Hand::try_from_str("TTT9", &rules), None);
+        assert_eq!(Hand::try_from_str("TTT91", &rules), None);
     }
     #[test]
     fn test_classify_hand(){
-        let hand_five_of_a_kind : Hand = Hand::try_from_str("AAAAA").unwrap();
-        let hand_four_of_a_kind : Hand= Hand::try_from_str("AA8AA").unwrap();
-        let hand_full_house : Hand = Hand::try_from_str("23332").unwrap();
-        let hand_three_of_a_kind : Hand = Hand::try_from_str("TTT98").unwrap();
-        let hand_two_pair : Hand = Hand::try_from_str("23432").unwrap();
-        let hand_one_pair : Hand = Hand::try_from_str("A23A4").unwrap();
-        let hand_high_card : Hand = Hand::try_from_str("23456").unwrap();
-
-        assert_eq!(classify_hand::<false>(&hand_five_of_a_kind), HandClassification::FiveOfAKind);
-        assert_eq!(classify_hand::<false>(&hand_four_of_a_kind), HandClassification::FourOfAKind);
-        assert_eq!(classify_hand::<false>(&hand_full_house), HandClassification::FullHouse);
-        assert_eq!(classify_hand::<false>(&hand_three_of_a_kind), HandClassification::ThreeOfAKind);
-        assert_eq!(classify_hand::<false>(&hand_two_pair), HandClassification::TwoPair);
-        assert_eq!(classify_hand::<false>(&hand_one_pair), HandClassification::OnePair);
-        assert_eq!(classify_hand::<false>(&hand_high_card), HandClassification::HighCard);
+        let rules = RuleSet::standard();
+        assert_eq!(classify("AAAAA", &rules), Some(HandClassification::FiveOfAKind));
+        assert_eq!(classify("AA8AA", &rules), Some(HandClassification::FourOfAKind));
+        assert_eq!(classify("23332", &rules), Some(HandClassification::FullHouse));
+        assert_eq!(classify("TTT98", &rules), Some(HandClassification::ThreeOfAKind));
+        assert_eq!(classify("23432", &rules), Some(HandClassification::TwoPair));
+        assert_eq!(classify("A23A4", &rules), Some(HandClassification::OnePair));
+        assert_eq!(classify("23456", &rules), Some(HandClassification::HighCard));
     }
 
     #[test]
     fn test_classify_hand_jokers(){
-        assert_eq!(classify_hand::<true>(&Hand::try_from_str("T55J5").unwrap()), HandClassification::FourOfAKind);
-        assert_eq!(classify_hand::<true>(&Hand::try_from_str("KTJJT").unwrap()), HandClassification::FourOfAKind);
-        assert_eq!(classify_hand::<true>(&Hand::try_from_str("QQQJA").unwrap()), HandClassification::FourOfAKind);
+        let rules = RuleSet::jokers();
+        assert_eq!(classify("T55J5", &rules), Some(HandClassification::FourOfAKind));
+        assert_eq!(classify("KTJJT", &rules), Some(HandClassification::FourOfAKind));
+        assert_eq!(classify("QQQJA", &rules), Some(HandClassification::FourOfAKind));
+        assert_eq!(classify("JJJJJ", &rules), Some(HandClassification::FiveOfAKind));
+        assert_eq!(classify("2345J", &rules), Some(HandClassification::OnePair));
+    }
+
+    #[test]
+    fn test_rule_set(){
+        assert_eq!(RuleSet::new("AKA", None, 5), None);
+        assert_eq!(RuleSet::new("AK", Some('Q'), 5), None);
+        assert_eq!(RuleSet::new("AK", None, 0), None);
+
+        // Three card hands with a wild ace that ranks highest
+        let rules = RuleSet::new("xyzA", Some('A'), 3).unwrap();
+        assert_eq!(classify_hand(&Hand::try_from_str("xAy", &rules).unwrap(), &rules), HandType(vec![2, 1]));
+        assert_eq!(classify_hand(&Hand::try_from_str("AAA", &rules).unwrap(), &rules), HandType(vec![3]));
+        assert_eq!(classify_hand(&Hand::try_from_str("xyz", &rules).unwrap(), &rules), HandType(vec![1, 1, 1]));
+        assert_eq!(classify("xyz", &rules), None);
+
+        const INPUT : &[u8] = indoc!{"
+            xyz 1
+            AAz 10
+            zzx 100
+        "}.as_bytes();
+        assert_eq!(calculate_total_winnings(INPUT, &rules), Some(1 + 2 * 100 + 3 * 10));
+    }
+
+    /// Best type reachable by replacing every wild card with any ranked card, which then counts as itself
+    fn classify_hand_brute_force(hand: &Hand, rules: &RuleSet) -> HandType{
+        let natural = RuleSet{ wild: None, ..rules.clone() };
+        hand.0
+            .iter()
+            .map(|card| if Some(*card) == rules.wild { rules.ranking.clone() } else { vec![*card] })
+            .multi_cartesian_product()
+            .map(|cards| classify_hand(&Hand(cards), &natural))
+            .max()
+            .unwrap_or(HandType(vec![]))
+    }
+
+    /// A rule set with a few ranked cards, possibly a wild one, and a hand drawn from it
+    fn rules_and_hand() -> impl Strategy<Value = (RuleSet, Hand)>{
+        (2usize..=5, 1usize..=6)
+            .prop_flat_map(|(card_count, hand_size)|{
+                let ranking: Vec<char> = "23456789TJQKA".chars().take(card_count).collect();
+                (
+                    Just(ranking.clone()),
+                    // One past the ranked cards stands for no wild card
+                    0..=card_count,
+                    prop::collection::vec(prop::sample::select(ranking), hand_size),
+                )
+            })
+            .prop_map(|(ranking, wild, cards)|{
+                let wild = ranking.get(wild).copied();
+                let rules = RuleSet::new(&ranking.iter().collect::<String>(), wild, cards.len()).unwrap();
+                (rules, Hand(cards))
+            })
+    }
+
+    proptest!{
+        #[test]
+        fn test_classify_hand_matches_substitution((rules, hand) in rules_and_hand()){
+            prop_assert_eq!(classify_hand(&hand, &rules), classify_hand_brute_force(&hand, &rules));
+        }
+
+        #[test]
+        fn test_classify_jokers_matches_substitution(cards in prop::collection::vec(prop::sample::select("J23456789TQKA".chars().collect::<Vec<char>>()), 5)){
+            prop_assume!(cards.iter().filter(|card| **card == 'J').count() <= 3);
+            let rules = RuleSet::jokers();
+            let hand = Hand(cards);
+            prop_assert_eq!(classify_hand(&hand, &rules), classify_hand_brute_force(&hand, &rules));
+        }
     }
 
     const BIDS : &[u8] = indoc!{"
@@ -261,25 +322,52 @@ mod tests{
 
     #[test]
     fn test_parse_bids(){
-        let bids = parse_bids(BIDS).unwrap();
+        let rules = RuleSet::standard();
+        let bids = parse_bids(BIDS, &rules).unwrap();
         assert_eq!(bids, [
-            Bid { hand: Hand::try_from_str("32T3K").unwrap(), bid: 765 },
-            Bid { hand: Hand::try_from_str("T55J5").unwrap(), bid: 684},
-            Bid { hand: Hand::try_from_str("KK677").unwrap(), bid: 28},
-            Bid { hand: Hand::try_from_str("KTJJT").unwrap(), bid: 220 },
-            Bid { hand: Hand::try_from_str("QQQJA").unwrap(), bid: 483 }, 
+            Bid { hand: Hand::try_from_str("32T3K", &rules).unwrap(), bid: 765 },
+            Bid { hand: Hand::try_from_str("T55J5", &rules).unwrap(), bid: 684},
+            Bid { hand: Hand::try_from_str("KK677", &rules).unwrap(), bid: 28},
+            Bid { hand: Hand::try_from_str("KTJJT", &rules).unwrap(), bid: 220 },
+            Bid { hand: Hand::try_from_str("QQQJA", &rules).unwrap(), bid: 483 },
         ]);
+        assert_eq!(normalize(b"32T3K   765\nKK677 28").unwrap(), "32T3K 765\nKK677 28\n");
     }
 
     #[test]
     fn test_winnings(){
-        let winnings = calculate_total_winnings::<_, false>(BIDS).unwrap();
+        let winnings = calculate_total_winnings(BIDS, &RuleSet::standard()).unwrap();
         assert_eq!(winnings, 6440);
     }
 
     #[test]
     fn test_winnings_with_jokers(){
-        let winnings = calculate_total_winnings::<_, true>(BIDS).unwrap();
+        let winnings = calculate_total_winnings(BIDS, &RuleSet::jokers()).unwrap();
         assert_eq!(winnings, 5905);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_generated(){
+        let input = generate(&mut Rng::new(7), 500);
+        let bids = parse_bids(input.as_bytes(), &RuleSet::jokers()).unwrap();
+        assert_eq!(bids.len(), 500);
+
+        // Every rank is used exactly once, so the winnings are bounded by the largest bids on the highest ranks
+        let winnings = calculate_total_winnings(input.as_bytes(), &RuleSet::jokers()).unwrap();
+        let mut sorted: Vec<usize> = bids.iter().map(|bid| bid.bid).collect();
+        sorted.sort_unstable();
+        let most: usize = sorted.iter().enumerate().map(|(rank, bid)| bid * (rank + 1)).sum();
+        assert!(winnings <= most);
+        assert!(calculate_total_winnings(input.as_bytes(), &RuleSet::standard()).unwrap() <= most);
+    }
+
+    proptest!{
+        #[test]
+        fn test_parse_bid_round_trip(cards in prop::collection::vec(prop::sample::select("23456789TJQKA".chars().collect::<Vec<_>>()), 5), bid in any::<usize>()){
+            let rules = RuleSet::standard();
+            let bid = Bid{ hand: Hand(cards), bid };
+            prop_assert_eq!(parse_bid(&bid.to_string(), &rules), Some(bid));
+        }
+    }
+
+}
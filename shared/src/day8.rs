@@ -1,9 +1,12 @@
 use std::collections::HashMap;
 
+use thiserror::Error;
+
+use crate::{generate::Rng, visualize::{self, Frame}};
+
 use nom::{
     IResult,
     character::complete::{alphanumeric1, char, multispace0},
-    bytes::complete::take_while,
     branch::alt,
     multi::many1,
     combinator::{value,map},
@@ -16,6 +19,12 @@ enum Command{
     R
 }
 
+impl std::fmt::Display for Command{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        f.write_str(match self { Command::L => "L", Command::R => "R" })
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 struct NodeDescription<'a>{
     name: &'a str,
@@ -23,6 +32,12 @@ struct NodeDescription<'a>{
     right: &'a str,
 }
 
+impl std::fmt::Display for NodeDescription<'_>{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        write!(f, "{} = ({}, {})", self.name, self.left, self.right)
+    }
+}
+
 fn parse_command(input: &str) -> IResult<&str, Command>{
     alt((value(Command::L, char('L')), value(Command::R, char('R'))))(input)
 }
@@ -31,7 +46,7 @@ fn parse_commands(input: &str) -> IResult<&str, Vec<Command>>{
     many1(parse_command)(input)
 }
 
-fn parse_node(input: &str) -> IResult<&str, NodeDescription>{
+fn parse_node(input: &str) -> IResult<&str, NodeDescription<'_>>{
     map(
         tuple((
             alphanumeric1,
@@ -53,97 +68,457 @@ fn parse_node(input: &str) -> IResult<&str, NodeDescription>{
 }
 
 
-#[derive(Eq, PartialEq, Hash, Debug)]
-struct NodeId(String);
+/// Assigns dense indices to node names in order of first appearance
+#[derive(Debug, Default)]
+struct Interner{
+    indices: HashMap<String, u32>,
+    names: Vec<String>,
+}
 
-impl From<&str> for NodeId{
-    fn from(value: &str) -> Self {
-        Self(String::from(value))
+impl Interner{
+    fn intern(&mut self, name: &str) -> u32{
+        if let Some(index) = self.indices.get(name){
+            return *index;
+        }
+
+        let index = self.names.len() as u32;
+        self.indices.insert(name.into(), index);
+        self.names.push(name.into());
+        index
     }
-}
 
-#[derive(Debug, PartialEq, Eq)]
-struct Node{
-    left: NodeId,
-    right: NodeId,
+    fn get(&self, name: &str) -> Option<u32>{
+        self.indices.get(name).copied()
+    }
+
+    fn name(&self, index: u32) -> &str{
+        &self.names[index as usize]
+    }
+
+    fn len(&self) -> usize{
+        self.names.len()
+    }
 }
 
-struct Map{
+/// Network compiled to dense node indices
+pub struct Map{
     commands: Vec<Command>,
-    nodes: std::collections::HashMap<NodeId, Node>
+    interner: Interner,
+    /// Left and right neighbour of every node, indexed by node and then by command
+    adjacency: Vec<[u32; 2]>,
+    /// Nodes ending in A, where the ghosts start, in order of definition
+    ghost_starts: Vec<u32>,
+    /// Whether each node ends in Z, where the ghosts want to end up
+    ghost_ends: Vec<bool>,
 }
 
-fn parse_map<R: std::io::BufRead>(input: R) -> Option<Map>{
-    let mut lines = input.lines();
-    let (_, commands) = parse_commands(&lines.next()?.ok()?).ok()?;
-    lines.next()?.ok()?;
+impl Map{
+    fn next(&self, node: u32, command: Command) -> u32{
+        self.adjacency[node as usize][command as usize]
+    }
+
+    /// Node reached from every node after following all commands once, by name.
+    /// The walks cannot skip ahead with it since they need every step to find their end node hits.
+    pub fn pass_jump_table(&self) -> HashMap<&str, &str>{
+        (0..self.adjacency.len() as u32)
+            .map(|node| (self.interner.name(node), self.interner.name(self.commands.iter().fold(node, |node, command| self.next(node, *command)))))
+            .collect()
+    }
+}
+
+/// Nodes are written sorted by name, so networks that only differ in the order of their definitions look the same
+impl std::fmt::Display for Map{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        for command in self.commands.iter(){
+            write!(f, "{}", command)?;
+        }
+        writeln!(f, "\n")?;
 
+        let mut nodes: Vec<u32> = (0..self.adjacency.len() as u32).collect();
+        nodes.sort_by_key(|node| self.interner.name(*node));
+        for node in nodes{
+            let [left, right] = self.adjacency[node as usize];
+            writeln!(f, "{}", NodeDescription{ name: self.interner.name(node), left: self.interner.name(left), right: self.interner.name(right) })?;
+        }
+        Ok(())
+    }
+}
 
-    let mut nodes = HashMap::new();
+pub fn parse_map(input: &str) -> Result<Map, Error>{
+    let mut lines = input.lines();
+    let command_line = lines.next().ok_or(Error::Parse)?;
+    let (_, commands) = parse_commands(command_line).map_err(|_| Error::Parse)?;
+    lines.next().ok_or(Error::Parse)?;
+
+    let mut interner = Interner::default();
+    let mut definitions : Vec<Option<[u32; 2]>> = Vec::new();
     for line in lines{
-        let input = line.ok()?;
-        let (_, node) = parse_node(&input).ok()?;
-        nodes.insert(NodeId(node.name.into()), Node{
-            left: NodeId(node.left.into()),
-            right: NodeId(node.right.into()),
-        });
+        let (_, node) = parse_node(line).map_err(|_| Error::Parse)?;
+
+        let name = interner.intern(node.name);
+        let edges = [interner.intern(node.left), interner.intern(node.right)];
+        definitions.resize(interner.len(), None);
+        if definitions[name as usize].replace(edges).is_some(){
+            return Err(Error::DuplicateNode(node.name.into()));
+        }
     }
 
-    Some(Map{
+    let adjacency = definitions
+        .iter()
+        .enumerate()
+        .map(|(index, edges)| edges.ok_or_else(|| Error::UnknownNode(interner.name(index as u32).into())))
+        .collect::<Result<Vec<[u32; 2]>, Error>>()?;
+
+    let ghost_starts = (0..interner.len() as u32).filter(|node| interner.name(*node).ends_with('A')).collect();
+    let ghost_ends = interner.names.iter().map(|name| name.ends_with('Z')).collect();
+
+    Ok(Map{
         commands,
-        nodes
+        interner,
+        adjacency,
+        ghost_starts,
+        ghost_ends,
     })
 }
 
-#[aoc_2023_markup::aoc_task(2023, 8, 1)]
-fn follow_map<R: std::io::BufRead>(input: R) -> Option<usize>{
-    let map = parse_map(input)?;
-
-    let start = NodeId::from("AAA");
-    let end = NodeId::from("ZZZ");
+/// How a walk from a single start node behaves over time.
+/// The walk is fully determined by its (node, instruction index) state, so once a state repeats it cycles forever.
+#[derive(Debug, PartialEq, Eq)]
+struct WalkCycle{
+    /// Number of steps before the walk enters its cycle
+    prefix_length: usize,
+    cycle_length: usize,
+    /// Steps after which the walk stands on an end node, before it enters its cycle
+    prefix_hits: Vec<usize>,
+    /// Steps after which the walk stands on an end node during the first pass of its cycle.
+    /// Each of these repeats every cycle_length steps.
+    cycle_hits: Vec<usize>,
+}
 
-    let mut commands = map.commands.iter().cycle().enumerate();
-    let mut current = &start;
-    loop{
-        let (step, command) = commands.next().unwrap();
-        execute_command(&map, &mut current, command);
-        if *current == end{
-            return Some(step + 1);
+impl WalkCycle{
+    fn is_hit(&self, step: usize) -> bool{
+        if step < self.prefix_length{
+            self.prefix_hits.binary_search(&step).is_ok()
+        } else{
+            let offset = self.prefix_length + (step - self.prefix_length) % self.cycle_length;
+            self.cycle_hits.binary_search(&offset).is_ok()
         }
     }
+
+    /// First step after the start that ends on an end node
+    fn first_hit(&self) -> Option<usize>{
+        self.prefix_hits
+            .iter()
+            .copied()
+            .find(|step| *step > 0)
+            .or_else(|| self.cycle_hits.iter().map(|step| if *step == 0 { self.cycle_length } else { *step }).min())
+    }
 }
 
-fn execute_command<'a, 'b>(map: &'a Map, node: &mut &'b NodeId, command: &Command) where 'a : 'b{
-    *node = match command{
-        Command::L => &map.nodes[node].left,
-        Command::R => &map.nodes[node].right
-    };
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error{
+    #[error("failed to parse the map")]
+    Parse,
+    #[error("node {0} is referenced but never defined")]
+    UnknownNode(String),
+    #[error("node {0} is defined more than once")]
+    DuplicateNode(String),
+    #[error("there are no start nodes")]
+    NoStartNodes,
+    #[error("the walk from {0} never reaches an end node")]
+    NeverReachesEnd(String),
+    #[error("the walks never stand on end nodes at the same time")]
+    NoSimultaneousArrival,
+    #[error("the number of steps does not fit in a usize")]
+    Overflow,
 }
 
-fn count_steps(map: &Map, start: &NodeId) -> usize{
-    let mut commands = map.commands.iter().cycle().enumerate();
+/// Walks from the start until the (node, instruction index) state repeats, recording every visit to an end node
+fn analyze_walk(map: &Map, start: u32, is_end: impl Fn(u32) -> bool) -> WalkCycle{
+    let pass_length = map.commands.len();
+
+    // Only the node at the start of every pass is remembered, which finds a period that is a whole number of passes.
+    // The shortest period divides it and is narrowed down afterwards from the recorded path.
+    let mut pass_start_seen = vec![u32::MAX; map.adjacency.len()];
+    let mut path = Vec::new();
     let mut current = start;
-    loop{
-        let (step, command) = commands.next().unwrap();
-        execute_command(map, &mut current, command);
-        if current.0.ends_with("Z"){
-            return step + 1;
+    let (first_pass, repeated_pass) = loop{
+        let pass = (path.len() / pass_length) as u32;
+        let seen = &mut pass_start_seen[current as usize];
+        if *seen != u32::MAX{
+            break (*seen as usize, pass as usize);
+        }
+        *seen = pass;
+
+        for command in map.commands.iter(){
+            path.push(current);
+            current = map.next(current, *command);
+        }
+
+        visualize::emit(|| pass_frame(map, start, pass as usize, &path[path.len() - pass_length..], &is_end));
+    };
+
+    // The shortest period keeps the instruction index, so it is a whole number of passes as well
+    let passes = repeated_pass - first_pass;
+    let cycle_length = (1..=passes)
+        .filter(|candidate| passes % candidate == 0)
+        .map(|candidate| candidate * pass_length)
+        .find(|candidate| (first_pass * pass_length..repeated_pass * pass_length - candidate).all(|step| path[step] == path[step + candidate]))
+        .unwrap();
+
+    // Extend the cycle backwards for as long as the path already repeats
+    let mut prefix_length = first_pass * pass_length;
+    while prefix_length > 0 && path[prefix_length - 1] == path[prefix_length - 1 + cycle_length]{
+        prefix_length -= 1;
+    }
+
+    let hits = (0..prefix_length + cycle_length).filter(|step| is_end(path[*step]));
+    let (prefix_hits, cycle_hits) = hits.partition(|hit| *hit < prefix_length);
+    WalkCycle{
+        prefix_length,
+        cycle_length,
+        prefix_hits,
+        cycle_hits,
+    }
+}
+
+/// Nodes visited during a single pass over the commands, with the end nodes highlighted
+fn pass_frame(map: &Map, start: u32, pass: usize, nodes: &[u32], is_end: impl Fn(u32) -> bool) -> Frame{
+    const NODES_PER_LINE: usize = 16;
+
+    let mut frame = Frame::new(format!("Walk from {}, pass {}", map.interner.name(start), pass + 1));
+    for (y, line) in nodes.chunks(NODES_PER_LINE).enumerate(){
+        let mut row = Vec::new();
+        for node in line{
+            let name = map.interner.name(*node);
+            if is_end(*node){
+                frame.highlight_span(row.len(), y, name.chars().count(), visualize::HIGHLIGHT);
+            }
+            row.extend(name.chars());
+            row.push(' ');
+        }
+        row.pop();
+        frame.rows.push(row);
+    }
+
+    frame
+}
+
+/// Finds the first step after the start at which every walk stands on an end node
+fn first_simultaneous_hit(walks: &[WalkCycle]) -> Result<usize, Error>{
+    use crate::math::{crt, Congruence, CrtError};
+
+    // Before every walk is inside its cycle, a simultaneous hit has to be a prefix hit of the walk with the longest prefix
+    let longest_prefix = walks.iter().max_by_key(|walk| walk.prefix_length).ok_or(Error::NoStartNodes)?;
+    let prefix_hit = longest_prefix.prefix_hits
+        .iter()
+        .copied()
+        .filter(|step| *step > 0)
+        .find(|step| walks.iter().all(|walk| walk.is_hit(*step)));
+    if let Some(step) = prefix_hit{
+        return Ok(step);
+    }
+
+    // After that every walk hits an end node exactly on a fixed set of residues modulo its cycle length
+    let mut candidates = vec![Congruence{ residue: 0, modulus: 1 }];
+    for walk in walks{
+        let mut combined = Vec::new();
+        for candidate in candidates.iter(){
+            for hit in walk.cycle_hits.iter(){
+                let hit = Congruence::new(*hit as i128, walk.cycle_length as i128).ok_or(Error::Overflow)?;
+                match crt([*candidate, hit]){
+                    Ok(congruence) => combined.push(congruence),
+                    Err(CrtError::NoSolution) => (),
+                    Err(CrtError::Overflow) => return Err(Error::Overflow),
+                }
+            }
         }
+
+        combined.sort_by_key(|congruence| (congruence.modulus, congruence.residue));
+        combined.dedup();
+        candidates = combined;
     }
+
+    // Smallest step past the prefixes satisfying any of the candidates
+    let lower_bound = longest_prefix.prefix_length.max(1) as i128;
+    let first = candidates
+        .iter()
+        .map(|congruence|{
+            let periods = num::Integer::div_ceil(&(lower_bound - congruence.residue).max(0), &congruence.modulus);
+            periods.checked_mul(congruence.modulus).and_then(|offset| offset.checked_add(congruence.residue))
+        })
+        .min()
+        .ok_or(Error::NoSimultaneousArrival)?
+        .ok_or(Error::Overflow)?;
+    usize::try_from(first).map_err(|_| Error::Overflow)
 }
 
-#[aoc_2023_markup::aoc_task(2023, 8, 2)]
-fn follow_map_ghost<R: std::io::BufRead>(input: R) -> Option<usize>{
-    use num::Integer;
+#[aoc_2023_markup::aoc_task(2023, 8, 1, parse = parse_map, solve = steps_to_end)]
+pub fn follow_map(input: &str) -> Result<usize, Error>{
+    steps_to_end(&parse_map(input)?)
+}
+
+/// Steps from AAA to ZZZ
+pub fn steps_to_end(map: &Map) -> Result<usize, Error>{
+    let start = map.interner.get("AAA").ok_or(Error::UnknownNode("AAA".into()))?;
+    let end = map.interner.get("ZZZ").ok_or(Error::UnknownNode("ZZZ".into()))?;
+
+    let walk = analyze_walk(map, start, |node| node == end);
+    walk.first_hit().ok_or(Error::NeverReachesEnd("AAA".into()))
+}
+
+#[aoc_2023_markup::aoc_task(2023, 8, 2, parse = parse_map, solve = ghost_steps_to_end)]
+pub fn follow_map_ghost(input: &str) -> Result<usize, Error>{
+    ghost_steps_to_end(&parse_map(input)?)
+}
+
+/// Steps until the ghosts starting at every node ending in A all stand on nodes ending in Z
+pub fn ghost_steps_to_end(map: &Map) -> Result<usize, Error>{
+    let walks = map.ghost_starts
+        .iter()
+        .map(|start|{
+            let walk = analyze_walk(map, *start, |node| map.ghost_ends[node as usize]);
+            if walk.first_hit().is_none(){
+                return Err(Error::NeverReachesEnd(map.interner.name(*start).into()));
+            }
+            Ok(walk)
+        })
+        .collect::<Result<Vec<WalkCycle>, Error>>()?;
+
+    first_simultaneous_hit(&walks)
+}
 
+/// Moves every walker one step at a time until all of them stand on an end node at once.
+/// The walkers are back in an earlier state after commands × nodes^walkers steps, so walking that far without success means they never will.
+fn walk_together(map: &Map, starts: &[u32], is_end: impl Fn(u32) -> bool) -> Option<usize>{
+    let states = starts
+        .iter()
+        .try_fold(map.commands.len(), |states, _| states.checked_mul(map.adjacency.len()))
+        .unwrap_or(usize::MAX);
+
+    let mut nodes = starts.to_vec();
+    for (step, command) in map.commands.iter().cycle().enumerate().take(states){
+        for node in nodes.iter_mut(){
+            *node = map.next(*node, *command);
+        }
+        if nodes.iter().all(|node| is_end(*node)){
+            return Some(step + 1);
+        }
+    }
+    None
+}
+
+#[aoc_2023_markup::aoc_task(2023, 8, 1, "reference")]
+fn follow_map_reference(input: &str) -> Result<usize, Error>{
+    let map = parse_map(input)?;
+
+    let start = map.interner.get("AAA").ok_or(Error::UnknownNode("AAA".into()))?;
+    let end = map.interner.get("ZZZ").ok_or(Error::UnknownNode("ZZZ".into()))?;
+    walk_together(&map, &[start], |node| node == end).ok_or(Error::NeverReachesEnd("AAA".into()))
+}
+
+#[aoc_2023_markup::aoc_task(2023, 8, 2, "reference")]
+fn follow_map_ghost_reference(input: &str) -> Result<usize, Error>{
     let map = parse_map(input)?;
-    let start_nodes : Vec<&NodeId> = map.nodes.iter().map(|(name, _)| name).filter(|name| name.0.ends_with("A")).collect();
-    start_nodes.iter().map(|start_node| count_steps(&map, start_node)).reduce(|a, b| a.lcm(&b))
+    if map.ghost_starts.is_empty(){
+        return Err(Error::NoStartNodes);
+    }
+    walk_together(&map, &map.ghost_starts, |node| map.ghost_ends[node as usize]).ok_or(Error::NoSimultaneousArrival)
+}
+
+/// Shape of a generated network
+#[derive(Debug, Clone)]
+struct NetworkShape{
+    command_length: usize,
+    /// Every ghost walks from its start node around a ring of this many nodes, the last of which is its end node.
+    /// The first ghost starts at AAA and ends at ZZZ.
+    ring_lengths: Vec<usize>,
+}
+
+/// Network in which every ghost reaches its end node after exactly its ring length in steps, and every multiple of it.
+/// Half of the ring nodes have a twin that one of the commands leads to instead, so the commands matter for the path but not for the timing.
+fn generate_network(rng: &mut Rng, shape: &NetworkShape) -> String{
+    // Names of at least three letters that neither end in A nor in Z
+    let mut next_name = 0usize;
+    let mut fresh_name = ||{
+        let mut name = String::new();
+        let mut rest = next_name / 24;
+        while rest > 0 || name.len() < 2{
+            name.push(char::from(b'A' + (rest % 26) as u8));
+            rest /= 26;
+        }
+        name.push(char::from(b'B' + (next_name % 24) as u8));
+        next_name += 1;
+        name
+    };
+
+    let mut nodes: Vec<(String, String, String)> = Vec::new();
+    for (ghost, ring_length) in shape.ring_lengths.iter().enumerate(){
+        let ring_length = (*ring_length).max(1);
+        let (start, end) = match ghost{
+            0 => ("AAA".to_string(), "ZZZ".to_string()),
+            // Digits keep them apart from the other names
+            _ => (format!("{:02}A", ghost), format!("{:02}Z", ghost)),
+        };
+
+        let mut ring: Vec<String> = (1..ring_length).map(|_| fresh_name()).collect();
+        ring.push(end);
+
+        // Twins lead to the same node as the original, but only one command goes through them
+        let twins: Vec<Option<String>> = ring.iter().enumerate().map(|(index, _)| (index + 1 < ring_length && rng.chance(0.5)).then(&mut fresh_name)).collect();
+        let edges = |index: usize|{
+            let next = &ring[index % ring_length];
+            match &twins[index % ring_length]{
+                Some(twin) => (next.clone(), twin.clone()),
+                None => (next.clone(), next.clone()),
+            }
+        };
+
+        nodes.push((start, edges(0).0, edges(0).1));
+        for (index, name) in ring.iter().enumerate(){
+            let (left, right) = edges(index + 1);
+            nodes.push((name.clone(), left.clone(), right.clone()));
+            if let Some(twin) = &twins[index]{
+                nodes.push((twin.clone(), left, right));
+            }
+        }
+    }
+    rng.shuffle(&mut nodes);
+
+    let commands: String = (0..shape.command_length.max(1)).map(|_| if rng.chance(0.5) { 'L' } else { 'R' }).collect();
+    let mut result = format!("{}\n\n", commands);
+    for (name, left, right) in nodes{
+        result.push_str(&format!("{}\n", NodeDescription{ name: &name, left: &left, right: &right }));
+    }
+    result
+}
+
+/// Network of up to five ghosts with rings of size/2 to size nodes, so the answer to part 2 stays well within 64 bits
+pub fn generate(rng: &mut Rng, size: usize) -> String{
+    let size = size.max(2);
+    let shape = NetworkShape{
+        command_length: rng.range(1..size / 4 + 2),
+        ring_lengths: (0..rng.range(1..6)).map(|_| rng.range(size / 2..size)).collect(),
+    };
+    generate_network(rng, &shape)
+}
+
+pub fn fuzz(data: &[u8]){
+    if let Ok(text) = std::str::from_utf8(data){
+        let _ = parse_map(text);
+    }
+}
+
+/// Rewrites the network with its nodes sorted by name
+pub fn normalize(data: &[u8]) -> Option<String>{
+    parse_map(std::str::from_utf8(data).ok()?).ok().map(|map| map.to_string())
 }
 
 #[cfg(test)]
 mod tests{
     use indoc::indoc;
+    use proptest::prelude::*;
+    use crate::generate::Rng;
 
     use super::*;
 
@@ -168,7 +543,7 @@ mod tests{
         });
     }
 
-    const INPUT : &[u8] = indoc! {"
+    const INPUT : &str = indoc! {"
         RL
 
         AAA = (BBB, CCC)
@@ -178,42 +553,241 @@ mod tests{
         EEE = (EEE, EEE)
         GGG = (GGG, GGG)
         ZZZ = (ZZZ, ZZZ)       
-    "}.as_bytes();
+    "};
 
     #[test]
     fn test_parse_map(){
 
         let map = parse_map(INPUT).unwrap();
-        assert_eq!(map.nodes.len(), 7);
-        assert_eq!(map.nodes.get(&NodeId::from("AAA")), Some(Node{
-            left: NodeId::from("BBB"),
-            right: NodeId::from("CCC")
-        }).as_ref());
+        assert_eq!(map.adjacency.len(), 7);
+
+        let node = |name| map.interner.get(name).unwrap();
+        assert_eq!(map.adjacency[node("AAA") as usize], [node("BBB"), node("CCC")]);
+        assert_eq!(map.adjacency[node("ZZZ") as usize], [node("ZZZ"), node("ZZZ")]);
+    }
+
+    #[test]
+    fn test_parse_map_ghost_nodes(){
+        let map = parse_map(GHOST_INPUT).unwrap();
+        let names : Vec<&str> = map.ghost_starts.iter().map(|node| map.interner.name(*node)).collect();
+        assert_eq!(names, ["11A", "22A"]);
+
+        let ends : Vec<&str> = (0..map.adjacency.len() as u32).filter(|node| map.ghost_ends[*node as usize]).map(|node| map.interner.name(node)).collect();
+        assert_eq!(ends, ["11Z", "22Z"]);
+    }
+
+    #[test]
+    fn test_pass_jump_table(){
+        let map = parse_map(GHOST_INPUT).unwrap();
+        let jump_table = map.pass_jump_table();
+        assert_eq!(jump_table.len(), 8);
+        assert_eq!(jump_table["11A"], "11Z");
+        assert_eq!(jump_table["11Z"], "11Z");
+        assert_eq!(jump_table["22A"], "22C");
+        assert_eq!(jump_table["22C"], "22B");
     }
 
     #[test]
     fn test_follow_map(){
         let count = follow_map(INPUT).unwrap();
         assert_eq!(count, 2);
+        assert_eq!(follow_map_reference(INPUT), Ok(2));
     }
 
+    const GHOST_INPUT : &str = indoc!{"
+        LR
+
+        11A = (11B, XXX)
+        11B = (XXX, 11Z)
+        11Z = (11B, XXX)
+        22A = (22B, XXX)
+        22B = (22C, 22C)
+        22C = (22Z, 22Z)
+        22Z = (22B, 22B)
+        XXX = (XXX, XXX)
+    "};
+
     #[test]
     fn test_follow_map_ghosts(){
-        const INPUT : &[u8] = indoc!{"
-            LR
-
-            11A = (11B, XXX)
-            11B = (XXX, 11Z)
-            11Z = (11B, XXX)
-            22A = (22B, XXX)
-            22B = (22C, 22C)
-            22C = (22Z, 22Z)
-            22Z = (22B, 22B)
-            XXX = (XXX, XXX)
-        "}.as_bytes();
-
-        let count = follow_map_ghost(INPUT).unwrap();
+        let count = follow_map_ghost(GHOST_INPUT).unwrap();
         assert_eq!(count, 6);
+        assert_eq!(follow_map_ghost_reference(GHOST_INPUT), Ok(6));
+    }
+
+    #[test]
+    fn test_analyze_walk(){
+        let map = parse_map(GHOST_INPUT).unwrap();
+        let is_end = |node: u32| map.ghost_ends[node as usize];
+        let node = |name| map.interner.get(name).unwrap();
+
+        assert_eq!(analyze_walk(&map, node("11A"), is_end), WalkCycle{
+            prefix_length: 1,
+            cycle_length: 2,
+            prefix_hits: vec![],
+            cycle_hits: vec![2],
+        });
+
+        assert_eq!(analyze_walk(&map, node("22A"), is_end), WalkCycle{
+            prefix_length: 1,
+            cycle_length: 6,
+            prefix_hits: vec![],
+            cycle_hits: vec![3, 6],
+        });
+    }
+
+    #[test]
+    fn test_walk_frames(){
+        let map = parse_map(GHOST_INPUT).unwrap();
+        let start = map.interner.get("22A").unwrap();
+        let (_, frames) = visualize::record(|| analyze_walk(&map, start, |node| map.ghost_ends[node as usize]));
+
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[1].to_text(), "Walk from 22A, pass 2\n22C 22Z\n");
+        assert_eq!(frames[1].highlights, [(4, 0, visualize::HIGHLIGHT), (5, 0, visualize::HIGHLIGHT), (6, 0, visualize::HIGHLIGHT)]);
+    }
+
+    #[test]
+    fn test_follow_map_ghosts_offset_cycles(){
+        // 1A first reaches 1Z after 5 steps and then every 3 steps, 2A reaches 2Z every 2 steps.
+        // Taking the LCM of the first arrivals would give 10, but the walks first meet after 8 steps.
+        const INPUT : &str = indoc!{"
+            L
+
+            1A = (1B, 1B)
+            1B = (1C, 1C)
+            1C = (1D, 1D)
+            1D = (1E, 1E)
+            1E = (1Z, 1Z)
+            1Z = (1F, 1F)
+            1F = (1G, 1G)
+            1G = (1Z, 1Z)
+            2A = (2B, 2B)
+            2B = (2Z, 2Z)
+            2Z = (2B, 2B)
+        "};
+
+        assert_eq!(follow_map_ghost(INPUT), Ok(8));
+        assert_eq!(follow_map_ghost_reference(INPUT), Ok(8));
+    }
+
+    #[test]
+    fn test_follow_map_ghosts_prefix_hit(){
+        // Both walks are on an end node after 2 steps, before 1A has entered its cycle
+        const INPUT : &str = indoc!{"
+            L
+
+            1A = (1B, 1B)
+            1B = (1Z, 1Z)
+            1Z = (1C, 1C)
+            1C = (1D, 1D)
+            1D = (1C, 1C)
+            2A = (2B, 2B)
+            2B = (2Z, 2Z)
+            2Z = (2B, 2B)
+        "};
+
+        assert_eq!(follow_map_ghost(INPUT), Ok(2));
+        assert_eq!(follow_map_ghost_reference(INPUT), Ok(2));
+    }
+
+    #[test]
+    fn test_follow_map_ghosts_degenerate(){
+        const NEVER_ENDS : &str = indoc!{"
+            L
+
+            1A = (1Z, 1Z)
+            1Z = (1A, 1A)
+            2A = (2A, 2A)
+        "};
+        assert_eq!(follow_map_ghost(NEVER_ENDS), Err(Error::NeverReachesEnd("2A".into())));
+
+        // Odd steps for 1A, even steps for 2A
+        const NEVER_MEET : &str = indoc!{"
+            L
+
+            1A = (1Z, 1Z)
+            1Z = (1A, 1A)
+            2A = (2B, 2B)
+            2B = (2Z, 2Z)
+            2Z = (2B, 2B)
+        "};
+        assert_eq!(follow_map_ghost(NEVER_MEET), Err(Error::NoSimultaneousArrival));
+        assert_eq!(follow_map_ghost_reference(NEVER_MEET), Err(Error::NoSimultaneousArrival));
+
+        const UNKNOWN_NODE : &str = indoc!{"
+            L
+
+            1A = (1B, 1B)
+        "};
+        assert_eq!(follow_map_ghost(UNKNOWN_NODE), Err(Error::UnknownNode("1B".into())));
+
+        const NO_START : &str = indoc!{"
+            L
+
+            1Z = (1Z, 1Z)
+        "};
+        assert_eq!(follow_map_ghost(NO_START), Err(Error::NoStartNodes));
+
+        const DUPLICATE_NODE : &str = indoc!{"
+            L
+
+            1A = (1Z, 1Z)
+            1Z = (1A, 1A)
+            1A = (1A, 1A)
+        "};
+        assert_eq!(follow_map_ghost(DUPLICATE_NODE), Err(Error::DuplicateNode("1A".into())));
+    }
+
+    #[test]
+    fn test_follow_map_unreachable(){
+        const INPUT : &str = indoc!{"
+            L
+
+            AAA = (BBB, BBB)
+            BBB = (AAA, AAA)
+            ZZZ = (ZZZ, ZZZ)
+        "};
+        assert_eq!(follow_map(INPUT), Err(Error::NeverReachesEnd("AAA".into())));
+        assert_eq!(follow_map_reference(INPUT), Err(Error::NeverReachesEnd("AAA".into())));
+    }
+
+
+    #[test]
+    fn test_generated(){
+        let shape = NetworkShape{ command_length: 7, ring_lengths: vec![12, 18, 5, 1, 49] };
+        let input = generate_network(&mut Rng::new(8), &shape);
+        assert_eq!(follow_map(&input), Ok(12));
+        assert_eq!(follow_map_ghost(&input), Ok(crate::math::lcm_all([12, 18, 5, 1, 49]).unwrap()));
+
+        for seed in 0..10{
+            let input = generate(&mut Rng::new(seed), 40);
+            let map = parse_map(&input).unwrap();
+            assert!(!map.ghost_starts.is_empty());
+            assert!(follow_map_ghost(&input).is_ok());
+        }
+    }
+
+    proptest!{
+        #[test]
+        fn test_parse_node_round_trip(name in "[A-Z0-9]{1,4}", left in "[A-Z0-9]{1,4}", right in "[A-Z0-9]{1,4}"){
+            let node = NodeDescription{ name: &name, left: &left, right: &right };
+            let rendered = node.to_string();
+            prop_assert_eq!(parse_node(&rendered), Ok(("", node)));
+        }
+
+        #[test]
+        fn test_parse_commands_round_trip(commands in prop::collection::vec(prop::sample::select(vec![Command::L, Command::R]), 1..50)){
+            let rendered: String = commands.iter().map(Command::to_string).collect();
+            prop_assert_eq!(parse_commands(&rendered), Ok(("", commands)));
+        }
+
+        #[test]
+        fn test_parse_map_round_trip(seed in any::<u64>(), size in 2usize..12){
+            let input = generate(&mut Rng::new(seed), size);
+            let rendered = parse_map(&input).unwrap().to_string();
+            prop_assert_eq!(parse_map(&rendered).unwrap().to_string(), rendered.clone());
+            prop_assert_eq!(follow_map_ghost(&rendered).ok(), follow_map_ghost(&input).ok());
+        }
     }
 
 }
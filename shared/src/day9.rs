@@ -1,59 +1,296 @@
+use num::{BigInt, BigRational, One, Signed, Zero};
+use thiserror::Error;
 
-fn parse_line(line: &str) -> Option<Vec<isize>>{
-    line.split_ascii_whitespace().map(|entry| entry.parse::<isize>().ok()).collect()
+use crate::generate::Rng;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum SequenceError{
+    #[error("the sequence is empty")]
+    Empty,
+    #[error("the differences never reach all zeros")]
+    NeverConverges,
+    #[error("the value does not fit in an isize")]
+    Overflow,
+}
+
+/// Errors carry the line they were found on, counting from 1
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error{
+    #[error("failed to parse line {line}")]
+    Parse{ line: usize },
+    #[error("failed to extrapolate the sequence on line {line}: {inner}")]
+    Sequence{ line: usize, inner: SequenceError },
+}
+
+fn parse_line(line: &[u8]) -> Option<Vec<isize>>{
+    crate::input::fields(line).map(crate::input::parse::<isize>).collect()
+}
+
+pub fn parse_inputs(input: &[u8]) -> Result<Vec<Vec<isize>>, Error>{
+    crate::input::lines(input)
+        .enumerate()
+        .map(|(line_index, line)| parse_line(line).ok_or(Error::Parse{ line: line_index + 1 }))
+        .collect()
+}
+
+/// Difference table of a sequence, reduced to the first value of every level.
+/// That is all Newton's forward difference formula needs to evaluate the sequence at any position.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SequenceAnalysis{
+    /// leading_differences[level] is the first value of the difference row at that level, up to the last non-zero row
+    leading_differences: Vec<isize>,
+    len: usize,
+}
+
+impl SequenceAnalysis{
+    /// Builds the difference table once, failing when no row of differences is all zeros
+    pub fn new(sequence: &[isize]) -> Result<SequenceAnalysis, SequenceError>{
+        if sequence.is_empty(){
+            return Err(SequenceError::Empty);
+        }
+
+        // Difference in place, each pass leaves one fewer value at the front of the buffer
+        let mut row = sequence.to_vec();
+        let mut leading_differences = Vec::new();
+        while !row.iter().all(|value| *value == 0){
+            if row.len() == 1{
+                return Err(SequenceError::NeverConverges);
+            }
+
+            leading_differences.push(row[0]);
+            for i in 0..(row.len() - 1){
+                row[i] = row[i + 1].checked_sub(row[i]).ok_or(SequenceError::Overflow)?;
+            }
+            row.pop();
+        }
+
+        Ok(SequenceAnalysis{
+            leading_differences,
+            len: sequence.len(),
+        })
+    }
+
+    /// Degree of the polynomial generating the sequence, sequences of a constant (including zero) have degree 0
+    pub fn degree(&self) -> usize{
+        self.leading_differences.len().saturating_sub(1)
+    }
+
+    /// Value of the sequence at an index, which may lie outside the original sequence in either direction
+    pub fn value_at(&self, index: isize) -> Result<isize, SequenceError>{
+        // Newton's forward difference formula: f(x) = sum of binomial(x, level) * leading_differences[level]
+        let x = index as i128;
+        let mut binomial = 1i128;
+        let mut value = 0i128;
+        for (level, difference) in self.leading_differences.iter().enumerate(){
+            if level > 0{
+                // binomial(x, level) = binomial(x, level - 1) * (x - level + 1) / level, the division is always exact
+                binomial = binomial.checked_mul(x - level as i128 + 1).ok_or(SequenceError::Overflow)? / level as i128;
+            }
+
+            let term = binomial.checked_mul(*difference as i128).ok_or(SequenceError::Overflow)?;
+            value = value.checked_add(term).ok_or(SequenceError::Overflow)?;
+        }
+
+        isize::try_from(value).map_err(|_| SequenceError::Overflow)
+    }
+
+    /// Value the given number of steps past the end of the sequence
+    pub fn extrapolate_forward(&self, steps: usize) -> Result<isize, SequenceError>{
+        let index = (self.len - 1).checked_add(steps).ok_or(SequenceError::Overflow)?;
+        self.value_at(isize::try_from(index).map_err(|_| SequenceError::Overflow)?)
+    }
+
+    /// Value the given number of steps before the start of the sequence
+    pub fn extrapolate_backward(&self, steps: usize) -> Result<isize, SequenceError>{
+        let steps = isize::try_from(steps).map_err(|_| SequenceError::Overflow)?;
+        self.value_at(-steps)
+    }
+
+    /// Closed form polynomial in the sequence index, expanded from the Newton form
+    pub fn polynomial(&self) -> Polynomial{
+        let mut result = Polynomial::zero();
+
+        // Falling factorial x * (x - 1) * ... * (x - level + 1) / level!
+        let mut basis = Polynomial{ coefficients: vec![BigRational::one()] };
+        for (level, difference) in self.leading_differences.iter().enumerate(){
+            if level > 0{
+                let factor = Polynomial{
+                    coefficients: vec![
+                        BigRational::from_integer(BigInt::from(1 - level as isize)),
+                        BigRational::one(),
+                    ]
+                };
+                basis = basis.multiply(&factor).scale(&BigRational::new(BigInt::one(), BigInt::from(level)));
+            }
+
+            result = result.add(&basis.scale(&BigRational::from_integer(BigInt::from(*difference))));
+        }
+
+        result
+    }
 }
 
-fn parse_inputs<R: std::io::BufRead>(input: R) -> Option<Vec<Vec<isize>>>{
-    input.lines().map(|line| line.ok().and_then(|line| parse_line(&line))).collect()
+/// Polynomial with exact rational coefficients, coefficients[power] belongs to x^power
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Polynomial{
+    coefficients: Vec<BigRational>,
 }
 
-fn extrapolate_value_forward(input: &[isize]) -> isize{
-    if input.iter().all(|input| *input == 0){
-        // Base case: Extrapolation for all zero differences
-        0
-    } else{
-        // Recursive case: Extrapolation for nonzero differences
-        let mut differences = Vec::with_capacity(input.len() - 1);
-        for i in 0..(input.len()-1){
-            differences.push(input[i + 1] - input[i]);
+impl Polynomial{
+    /// Polynomial with the given coefficients, coefficients[power] belongs to x^power
+    pub fn new(coefficients: Vec<BigRational>) -> Polynomial{
+        Polynomial{ coefficients }.trimmed()
+    }
+
+    pub fn zero() -> Polynomial{
+        Polynomial{ coefficients: Vec::new() }
+    }
+
+    /// Coefficients up to the highest non-zero one, coefficients[power] belongs to x^power
+    pub fn coefficients(&self) -> &[BigRational]{
+        &self.coefficients
+    }
+
+    /// Highest power with a non-zero coefficient, None for the zero polynomial
+    pub fn degree(&self) -> Option<usize>{
+        self.coefficients.iter().rposition(|coefficient| !coefficient.is_zero())
+    }
+
+    pub fn evaluate(&self, x: &BigRational) -> BigRational{
+        self.coefficients.iter().rev().fold(BigRational::zero(), |acc, coefficient| acc * x + coefficient)
+    }
+
+    fn add(&self, other: &Polynomial) -> Polynomial{
+        let len = self.coefficients.len().max(other.coefficients.len());
+        let zero = BigRational::zero();
+        let coefficients = (0..len)
+            .map(|power| self.coefficients.get(power).unwrap_or(&zero) + other.coefficients.get(power).unwrap_or(&zero))
+            .collect();
+        Polynomial{ coefficients }.trimmed()
+    }
+
+    fn multiply(&self, other: &Polynomial) -> Polynomial{
+        if self.coefficients.is_empty() || other.coefficients.is_empty(){
+            return Polynomial::zero();
         }
 
-        let next_difference = extrapolate_value_forward(&differences);
-        input.last().unwrap() + next_difference
+        let mut coefficients = vec![BigRational::zero(); self.coefficients.len() + other.coefficients.len() - 1];
+        for (a_power, a) in self.coefficients.iter().enumerate(){
+            for (b_power, b) in other.coefficients.iter().enumerate(){
+                coefficients[a_power + b_power] += a * b;
+            }
+        }
+        Polynomial{ coefficients }.trimmed()
+    }
+
+    fn scale(&self, factor: &BigRational) -> Polynomial{
+        Polynomial{ coefficients: self.coefficients.iter().map(|coefficient| coefficient * factor).collect() }.trimmed()
+    }
+
+    fn trimmed(mut self) -> Polynomial{
+        while self.coefficients.last().is_some_and(|coefficient| coefficient.is_zero()){
+            self.coefficients.pop();
+        }
+        self
     }
 }
 
-fn extrapolate_value_backward(input: &[isize]) -> isize{
-    if input.iter().all(|input| *input == 0){
-        // Base case: Extrapolation for all zero differences
-        0
-    } else{
-        // Recursive case: Extrapolation for nonzero differences
-        let mut differences = Vec::with_capacity(input.len() - 1);
-        for i in 0..(input.len()-1){
-            differences.push(input[i + 1] - input[i]);
+impl std::fmt::Display for Polynomial{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        let mut terms = self.coefficients.iter().enumerate().rev().filter(|(_, coefficient)| !coefficient.is_zero()).peekable();
+        if terms.peek().is_none(){
+            return write!(f, "0");
         }
 
-        let next_difference = extrapolate_value_backward(&differences);
-        input.first().unwrap() - next_difference
+        let mut first = true;
+        for (power, coefficient) in terms{
+            if first{
+                if coefficient.is_negative(){
+                    write!(f, "-")?;
+                }
+            } else{
+                write!(f, " {} ", if coefficient.is_negative() { '-' } else { '+' })?;
+            }
+            first = false;
+
+            let magnitude = coefficient.abs();
+            if power == 0 || !magnitude.is_one(){
+                if magnitude.is_integer(){
+                    write!(f, "{}", magnitude)?;
+                } else{
+                    write!(f, "({})", magnitude)?;
+                }
+            }
+
+            match power{
+                0 => (),
+                1 => write!(f, "x")?,
+                _ => write!(f, "x^{}", power)?,
+            }
+        }
+
+        Ok(())
     }
 }
 
-#[aoc_2023_markup::aoc_task(2023, 9, 1)]
-fn part1<R: std::io::BufRead>(input: R) -> Option<isize>{
-    let input = parse_inputs(input)?;
-    Some(input.iter().map(|input| extrapolate_value_forward(input)).sum())
+fn sum_extrapolated(input: &[Vec<isize>], extrapolate: impl Fn(&SequenceAnalysis) -> Result<isize, SequenceError>) -> Result<isize, Error>{
+    input.iter().enumerate().try_fold(0isize, |sum, (line_index, sequence)|{
+        let line = line_index + 1;
+        let value = SequenceAnalysis::new(sequence)
+            .and_then(|analysis| extrapolate(&analysis))
+            .map_err(|inner| Error::Sequence{ line, inner })?;
+        sum.checked_add(value).ok_or(Error::Sequence{ line, inner: SequenceError::Overflow })
+    })
+}
+
+pub fn sum_next_values(sequences: &[Vec<isize>]) -> Result<isize, Error>{
+    sum_extrapolated(sequences, |analysis| analysis.extrapolate_forward(1))
+}
+
+pub fn sum_previous_values(sequences: &[Vec<isize>]) -> Result<isize, Error>{
+    sum_extrapolated(sequences, |analysis| analysis.extrapolate_backward(1))
+}
+
+#[aoc_2023_markup::aoc_task(2023, 9, 1, parse = parse_inputs, solve = sum_next_values)]
+pub fn part1(input: &[u8]) -> Result<isize, Error>{
+    sum_next_values(&parse_inputs(input)?)
+}
+
+#[aoc_2023_markup::aoc_task(2023, 9, 2, parse = parse_inputs, solve = sum_previous_values)]
+pub fn part2(input: &[u8]) -> Result<isize, Error>{
+    sum_previous_values(&parse_inputs(input)?)
+}
+
+/// Coefficients of a random polynomial of degree at most 5, coefficients[power] belongs to x^power
+fn random_polynomial(rng: &mut Rng) -> Vec<isize>{
+    (0..=rng.range(0..6)).map(|_| rng.range(0..11) as isize - 5).collect()
+}
+
+fn evaluate(coefficients: &[isize], x: isize) -> isize{
+    coefficients.iter().rev().fold(0, |acc, coefficient| acc * x + coefficient)
 }
 
-#[aoc_2023_markup::aoc_task(2023, 9, 2)]
-fn part2<R: std::io::BufRead>(input: R) -> Option<isize>{
-    let input = parse_inputs(input)?;
-    Some(input.iter().map(|input| extrapolate_value_backward(input)).sum())
+/// Lines of 21 values of random polynomials, all of which extrapolate exactly
+pub fn generate(rng: &mut Rng, size: usize) -> String{
+    let mut result = String::new();
+    for _ in 0..size{
+        let coefficients = random_polynomial(rng);
+        let values: Vec<String> = (0..=20).map(|x| evaluate(&coefficients, x).to_string()).collect();
+        result.push_str(&values.join(" "));
+        result.push('\n');
+    }
+    result
+}
+
+pub fn fuzz(data: &[u8]){
+    let _ = parse_inputs(data);
 }
 
 #[cfg(test)]
 mod test{
     use indoc::indoc;
+    use proptest::prelude::*;
+    use crate::generate::Rng;
     use super::*;
 
     const INPUT :&[u8]= indoc!{"
@@ -62,6 +299,10 @@ mod test{
         10 13 16 21 30 45
     "}.as_bytes();
 
+    fn analyze(sequence: &[isize]) -> SequenceAnalysis{
+        SequenceAnalysis::new(sequence).unwrap()
+    }
+
     #[test]
     fn test_parse_inputs(){
 
@@ -76,17 +317,103 @@ mod test{
     #[test]
     fn test_extrapolate_value_forward(){
         let input = parse_inputs(INPUT).unwrap();
-        assert_eq!(extrapolate_value_forward(&input[0]), 18);
-        assert_eq!(extrapolate_value_forward(&input[1]), 28);
-        assert_eq!(extrapolate_value_forward(&input[2]), 68);
+        assert_eq!(analyze(&input[0]).extrapolate_forward(1), Ok(18));
+        assert_eq!(analyze(&input[1]).extrapolate_forward(1), Ok(28));
+        assert_eq!(analyze(&input[2]).extrapolate_forward(1), Ok(68));
     }
 
     #[test]
     fn test_extrapolate_value_backward(){
         let input = parse_inputs(INPUT).unwrap();
-        assert_eq!(extrapolate_value_backward(&input[0]), -3);
-        assert_eq!(extrapolate_value_backward(&input[1]), 0);
-        assert_eq!(extrapolate_value_backward(&input[2]), 5);
+        assert_eq!(analyze(&input[0]).extrapolate_backward(1), Ok(-3));
+        assert_eq!(analyze(&input[1]).extrapolate_backward(1), Ok(0));
+        assert_eq!(analyze(&input[2]).extrapolate_backward(1), Ok(5));
+    }
+
+    #[test]
+    fn test_extrapolate_multiple_steps(){
+        let analysis = analyze(&[1, 3, 6, 10, 15, 21]);
+        assert_eq!(analysis.extrapolate_forward(0), Ok(21));
+        assert_eq!(analysis.extrapolate_forward(3), Ok(45));
+        assert_eq!(analysis.extrapolate_backward(0), Ok(1));
+        assert_eq!(analysis.extrapolate_backward(3), Ok(1));
+        assert_eq!(analysis.extrapolate_backward(4), Ok(3));
+    }
+
+    #[test]
+    fn test_degree(){
+        let input = parse_inputs(INPUT).unwrap();
+        assert_eq!(analyze(&input[0]).degree(), 1);
+        assert_eq!(analyze(&input[1]).degree(), 2);
+        assert_eq!(analyze(&input[2]).degree(), 3);
+        assert_eq!(analyze(&[4, 4, 4]).degree(), 0);
+        assert_eq!(analyze(&[0, 0]).degree(), 0);
+    }
+
+    #[test]
+    fn test_polynomial(){
+        let polynomial = analyze(&[1, 3, 6, 10, 15, 21]).polynomial();
+        assert_eq!(polynomial.degree(), Some(2));
+        assert_eq!(polynomial.to_string(), "(1/2)x^2 + (3/2)x + 1");
+
+        assert_eq!(analyze(&[0, 3, 6, 9, 12, 15]).polynomial().to_string(), "3x");
+        assert_eq!(analyze(&[5, 3, 1]).polynomial().to_string(), "-2x + 5");
+        assert_eq!(analyze(&[0, 0, 0]).polynomial().to_string(), "0");
+        assert_eq!(analyze(&[0, 0, 0]).polynomial().degree(), None);
+
+        let integer = |value: isize| BigRational::from_integer(BigInt::from(value));
+        let built = Polynomial::new(vec![integer(5), integer(-2), integer(0)]);
+        assert_eq!(built, analyze(&[5, 3, 1]).polynomial());
+        assert_eq!(built.coefficients(), [integer(5), integer(-2)]);
+    }
+
+    #[test]
+    fn test_polynomial_matches_extrapolation(){
+        let input = parse_inputs(INPUT).unwrap();
+        for sequence in input{
+            let analysis = analyze(&sequence);
+            let polynomial = analysis.polynomial();
+            for index in -5..15isize{
+                let expected = BigRational::from_integer(BigInt::from(analysis.value_at(index).unwrap()));
+                assert_eq!(polynomial.evaluate(&BigRational::from_integer(BigInt::from(index))), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_never_converges(){
+        assert_eq!(SequenceAnalysis::new(&[]), Err(SequenceError::Empty));
+        assert_eq!(SequenceAnalysis::new(&[7]), Err(SequenceError::NeverConverges));
+        assert_eq!(SequenceAnalysis::new(&[1, 2, 4, 8]), Err(SequenceError::NeverConverges));
+
+        const INPUT : &[u8] = indoc!{"
+            0 3 6 9 12 15
+            1 2 4 8
+        "}.as_bytes();
+        assert_eq!(part1(INPUT), Err(Error::Sequence{ line: 2, inner: SequenceError::NeverConverges }));
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_overflow(){
+        assert_eq!(SequenceAnalysis::new(&[isize::MIN, isize::MAX]), Err(SequenceError::Overflow));
+        assert_eq!(analyze(&[0, isize::MAX / 2, isize::MAX / 2 * 2]).extrapolate_forward(2), Err(SequenceError::Overflow));
+    }
+
+    #[test]
+    fn test_generated(){
+        let input = generate(&mut Rng::new(9), 50);
+        let mut rng = Rng::new(9);
+        let polynomials: Vec<Vec<isize>> = (0..50).map(|_| random_polynomial(&mut rng)).collect();
+        assert_eq!(part1(input.as_bytes()), Ok(polynomials.iter().map(|coefficients| evaluate(coefficients, 21)).sum()));
+        assert_eq!(part2(input.as_bytes()), Ok(polynomials.iter().map(|coefficients| evaluate(coefficients, -1)).sum()));
+    }
+
+    proptest!{
+        #[test]
+        fn test_parse_inputs_round_trip(sequences in prop::collection::vec(prop::collection::vec(any::<isize>(), 1..10), 0..10)){
+            let rendered: Vec<String> = sequences.iter().map(|sequence| sequence.iter().map(isize::to_string).collect::<Vec<_>>().join(" ")).collect();
+            prop_assert_eq!(parse_inputs(rendered.join("\n").as_bytes()), Ok(sequences));
+        }
+    }
+
+}
@@ -0,0 +1,364 @@
+//! Differential testing of the fast solutions against slow but obviously correct reference implementations.
+//! Every part with a reference variant runs on generated inputs, and an input on which the two disagree
+//! is shrunk to a small input that still shows the disagreement.
+
+use crate::{generate::{self, Generator, Rng}, AocTask, AOC_ENTRIES};
+
+/// Variant name of the reference implementation of a part
+pub const REFERENCE: &str = "reference";
+
+/// Whether a task is a reference implementation. References are written to be obviously correct, not fast,
+/// and are far too slow for real inputs, so runs on those leave them out unless asked for by name.
+pub fn is_reference(entry: &dyn AocTask) -> bool{
+    entry.variant() == Some(REFERENCE)
+}
+
+/// Runs a task on an input and returns everything it printed
+pub fn run_task(task: &dyn AocTask, input: &str) -> String{
+    let mut output = Vec::new();
+    // Writing to memory only fails if the task itself reports an error
+    if let Err(error) = task.invoke(input.as_bytes(), &mut output){
+        return format!("Error: {}\n", error);
+    }
+    String::from_utf8_lossy(&output).into_owned()
+}
+
+/// Whether two outputs give the same answer. Errors all count as the same answer,
+/// the implementations may well explain a bad input differently.
+fn agree(a: &str, b: &str) -> bool{
+    a == b || (a.starts_with("Error") && b.starts_with("Error"))
+}
+
+/// A fast implementation paired with the reference of the same part
+#[derive(Clone, Copy)]
+pub struct Pair<'a>{
+    pub fast: &'a dyn AocTask,
+    pub reference: &'a dyn AocTask,
+}
+
+impl Pair<'_>{
+    fn disagree(&self, input: &str) -> bool{
+        !agree(&run_task(self.fast, input), &run_task(self.reference, input))
+    }
+}
+
+/// Every implementation of a day that has a reference for its part, paired with that reference
+pub fn pairs(day: u32) -> Vec<Pair<'static>>{
+    let tasks: Vec<&'static dyn AocTask> = AOC_ENTRIES.iter().map(|entry| *entry as &dyn AocTask).filter(|entry| entry.day() == day).collect();
+    let mut pairs = Vec::new();
+    for reference in tasks.iter().filter(|task| is_reference(**task)){
+        for fast in tasks.iter().filter(|task| task.part() == reference.part() && !is_reference(**task)){
+            pairs.push(Pair{ fast: *fast, reference: *reference });
+        }
+    }
+    pairs.sort_by_key(|pair| (pair.fast.part(), pair.fast.variant()));
+    pairs
+}
+
+/// Days that have at least one reference implementation
+pub fn days_with_references() -> Vec<u32>{
+    let mut days: Vec<u32> = AOC_ENTRIES.iter().filter(|entry| is_reference(**entry)).map(|entry| entry.day()).collect();
+    days.sort_unstable();
+    days.dedup();
+    days
+}
+
+/// An input on which a fast implementation and its reference disagree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch{
+    pub task: String,
+    pub seed: u64,
+    pub size: usize,
+    /// The shrunk input
+    pub input: String,
+    pub fast_output: String,
+    pub reference_output: String,
+}
+
+impl std::fmt::Display for Mismatch{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        writeln!(f, "{} disagrees with its reference on seed {}, size {}, shrunk to:", self.task, self.seed, self.size)?;
+        write!(f, "{}", self.input)?;
+        writeln!(f, "fast: {}", self.fast_output.trim_end())?;
+        write!(f, "reference: {}", self.reference_output.trim_end())
+    }
+}
+
+/// Runs a pair on the generated input of a seed and size, shrinking the input when they disagree
+pub fn check(pair: Pair, generator: Generator, seed: u64, size: usize) -> Option<Mismatch>{
+    let input = generator(&mut Rng::new(seed), size);
+    if !pair.disagree(&input){
+        return None;
+    }
+
+    // The same seed at a smaller size is usually the quickest way to a smaller input
+    let (size, input) = (1..size)
+        .map(|size| (size, generator(&mut Rng::new(seed), size)))
+        .find(|(_, input)| pair.disagree(input))
+        .unwrap_or((size, input));
+    let input = shrink(&input, |candidate| pair.disagree(candidate));
+
+    Some(Mismatch{
+        task: crate::task_name(pair.fast),
+        seed,
+        size,
+        fast_output: run_task(pair.fast, &input),
+        reference_output: run_task(pair.reference, &input),
+        input,
+    })
+}
+
+fn join_lines(lines: &[String]) -> String{
+    lines.iter().flat_map(|line| [line.as_str(), "\n"]).collect()
+}
+
+/// Makes an input smaller for as long as it keeps failing: first whole lines are removed,
+/// then single words, then numbers are made smaller and characters are replaced with '.'.
+pub fn shrink(input: &str, fails: impl Fn(&str) -> bool) -> String{
+    let mut lines: Vec<String> = input.lines().map(String::from).collect();
+
+    loop{
+        let before = lines.clone();
+
+        // Remove runs of lines, halving the run length down to single lines
+        let mut run = lines.len().div_ceil(2).max(1);
+        loop{
+            let mut start = 0;
+            while start < lines.len(){
+                let end = (start + run).min(lines.len());
+                let candidate = [&lines[..start], &lines[end..]].concat();
+                if fails(&join_lines(&candidate)){
+                    lines = candidate;
+                } else{
+                    start += run;
+                }
+            }
+            if run == 1{
+                break;
+            }
+            run /= 2;
+        }
+
+        for index in 0..lines.len(){
+            shrink_line(&mut lines, index, &fails);
+        }
+
+        if lines == before{
+            return join_lines(&lines);
+        }
+    }
+}
+
+/// Shrinks the words of a single line in place
+fn shrink_line(lines: &mut [String], index: usize, fails: &impl Fn(&str) -> bool){
+    let attempt = |lines: &mut [String], line: String|{
+        let previous = std::mem::replace(&mut lines[index], line);
+        if fails(&join_lines(lines)){
+            true
+        } else{
+            lines[index] = previous;
+            false
+        }
+    };
+
+    // Remove words, an empty line has nothing left to remove
+    let mut word = 0;
+    while !lines[index].is_empty() && word < lines[index].split(' ').count(){
+        let words: Vec<&str> = lines[index].split(' ').collect();
+        let candidate = [&words[..word], &words[word + 1..]].concat().join(" ");
+        if !attempt(lines, candidate){
+            word += 1;
+        }
+    }
+
+    // Find the smallest value of every number that still fails, assuming smaller values fail less
+    for word in 0..lines[index].split(' ').count(){
+        let with_value = |lines: &[String], value: u64|{
+            let mut words: Vec<String> = lines[index].split(' ').map(String::from).collect();
+            words[word] = value.to_string();
+            words.join(" ")
+        };
+        let Ok(mut high) = lines[index].split(' ').nth(word).unwrap().parse::<u64>() else{
+            continue;
+        };
+        let mut low = 0;
+        while low < high{
+            let middle = low + (high - low) / 2;
+            let candidate = with_value(lines, middle);
+            if attempt(lines, candidate){
+                high = middle;
+            } else{
+                low = middle + 1;
+            }
+        }
+    }
+
+    // Blank out single characters, which is what shrinks grids
+    for position in 0..lines[index].len(){
+        let line = &lines[index];
+        if !line.is_char_boundary(position) || line[position..].starts_with(['.', ' ']){
+            continue;
+        }
+        let length = line[position..].chars().next().map_or(1, char::len_utf8);
+        let candidate = format!("{}.{}", &line[..position], &line[position + length..]);
+        attempt(lines, candidate);
+    }
+}
+
+#[derive(clap::Args)]
+pub struct DifferentialArgs{
+    /// Day to test, every day with a reference implementation when left out
+    #[arg(long)]
+    day: Option<u32>,
+
+    /// Number of generated inputs per day
+    #[arg(long, default_value_t = 50)]
+    runs: u64,
+
+    /// Seed of the first input, the following inputs use the seeds after it
+    #[arg(long, default_value_t = 1)]
+    seed: u64,
+
+    /// Largest input size, the sizes cycle from 1 up to it. Defaults to a size the references of the day handle quickly.
+    #[arg(long)]
+    size: Option<usize>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum DifferentialError{
+    #[error("there is no generator for day {0}")]
+    NoGenerator(u32),
+    #[error("day {0} has no reference implementation")]
+    NoReference(u32),
+    #[error("{0}")]
+    Mismatch(Box<Mismatch>),
+}
+
+/// Largest input size that the references of a day handle quickly
+fn default_size(day: u32) -> usize{
+    match day{
+        // Part 2 joins the races into one, which grows tenfold with every digit
+        6 => 2,
+        _ => 16,
+    }
+}
+
+/// Runs the pairs of a day on generated inputs, returning the number of inputs they all agreed on
+pub fn check_day(day: u32, runs: u64, seed: u64, size: usize) -> Result<u64, DifferentialError>{
+    let generator = generate::generator(day).ok_or(DifferentialError::NoGenerator(day))?;
+    let pairs = pairs(day);
+    if pairs.is_empty(){
+        return Err(DifferentialError::NoReference(day));
+    }
+
+    for run in 0..runs{
+        let size = 1 + (run as usize) % size.max(1);
+        for pair in pairs.iter(){
+            if let Some(mismatch) = check(*pair, generator, seed + run, size){
+                return Err(DifferentialError::Mismatch(Box::new(mismatch)));
+            }
+        }
+    }
+    Ok(runs)
+}
+
+pub fn differential(args: &DifferentialArgs) -> Result<(), DifferentialError>{
+    let days = match args.day{
+        Some(day) => vec![day],
+        None => days_with_references(),
+    };
+
+    for day in days{
+        let size = args.size.unwrap_or_else(|| default_size(day));
+        let runs = check_day(day, args.runs, args.seed, size)?;
+        println!("Day {}: {} inputs agree", day, runs);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    /// Task made from a closure over the input, for checking the harness itself
+    struct FakeTask{
+        variant: Option<&'static str>,
+        solve: fn(&str) -> Option<usize>,
+    }
+
+    impl AocTask for FakeTask{
+        fn year(&self) -> u32{ 2023 }
+        fn day(&self) -> u32{ 0 }
+        fn part(&self) -> u32{ 1 }
+        fn variant(&self) -> Option<&'static str>{ self.variant }
+        fn invoke(&self, input: &[u8], output: &mut dyn std::io::Write) -> std::io::Result<()>{
+            crate::run((self.solve)(&String::from_utf8_lossy(input)), output)
+        }
+    }
+
+    fn numbers(input: &str) -> impl Iterator<Item = usize> + '_{
+        input.split_ascii_whitespace().filter_map(|word| word.parse().ok())
+    }
+
+    fn random_numbers(rng: &mut Rng, size: usize) -> String{
+        (0..size).map(|_| format!("{} {}\n", rng.range(0..100), rng.range(0..100))).collect()
+    }
+
+    #[test]
+    fn test_shrink_lines_and_words(){
+        let shrunk = shrink("a\nb 7\nbad x y\nc\n", |input| input.contains("bad"));
+        assert_eq!(shrunk, "bad\n");
+
+        // Lines can shrink down to nothing while their count still matters
+        let shrunk = shrink("a\nb\nc\n", |input| input.lines().count() >= 2);
+        assert_eq!(shrunk, "\n\n");
+    }
+
+    #[test]
+    fn test_shrink_numbers(){
+        let fails = |input: &str| numbers(input).any(|number| number >= 10);
+        assert_eq!(shrink("1 2 300\n4 5 6\n", fails), "10\n");
+    }
+
+    #[test]
+    fn test_shrink_grid(){
+        let fails = |input: &str| input.lines().any(|line| line.contains("##"));
+        assert_eq!(shrink("#.#\n.##\n#..\n", fails), ".##\n");
+    }
+
+    #[test]
+    fn test_check_finds_and_shrinks_mismatch(){
+        // The fast sum forgets numbers above 90
+        let fast = FakeTask{ variant: None, solve: |input| Some(numbers(input).filter(|number| *number <= 90).sum()) };
+        let reference = FakeTask{ variant: Some(REFERENCE), solve: |input| Some(numbers(input).sum()) };
+        let pair = Pair{ fast: &fast, reference: &reference };
+
+        let mismatch = (1..20).find_map(|seed| check(pair, random_numbers, seed, 20)).unwrap();
+        assert_eq!(mismatch.input, "91\n");
+        assert_eq!(mismatch.fast_output, "Result: 0\n");
+        assert_eq!(mismatch.reference_output, "Result: 91\n");
+        assert_eq!(mismatch.task, "2023-0-1");
+
+        assert!(check(Pair{ fast: &reference, reference: &reference }, random_numbers, 1, 20).is_none());
+    }
+
+    #[test]
+    fn test_errors_agree(){
+        let fast = FakeTask{ variant: None, solve: |_| None };
+        let reference = FakeTask{ variant: Some(REFERENCE), solve: |input| numbers(input).next() };
+        let pair = Pair{ fast: &fast, reference: &reference };
+        assert!(!pair.disagree(""));
+        assert!(pair.disagree("5"));
+    }
+
+    #[test]
+    fn test_references_agree(){
+        let days = days_with_references();
+        assert_eq!(days, [5, 6, 8, 10, 11]);
+        for day in days{
+            if let Err(error) = check_day(day, 12, 1, default_size(day)){
+                panic!("day {}: {}", day, error);
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Optional explanations that tasks can give alongside their results.
+//! Lines are only formatted while explanations are being collected.
+
+use std::cell::RefCell;
+
+thread_local!{
+    static LINES: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+/// Whether explanations are being collected, for tasks that need extra work to explain themselves
+pub fn enabled() -> bool{
+    LINES.with_borrow(|lines| lines.is_some())
+}
+
+/// Adds a line to the explanation. The line is only built when explanations are being collected.
+pub fn line(build: impl FnOnce() -> String){
+    LINES.with_borrow_mut(|lines|{
+        if let Some(lines) = lines{
+            lines.push(build());
+        }
+    });
+}
+
+/// Runs f and returns the explanation lines it added
+pub fn collect<T>(f: impl FnOnce() -> T) -> (T, Vec<String>){
+    let previous = LINES.replace(Some(Vec::new()));
+    let result = f();
+    let lines = LINES.replace(previous).unwrap();
+    (result, lines)
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn test_collect(){
+        line(|| panic!("lines must not be built without a collector"));
+
+        let (result, lines) = collect(||{
+            line(|| "first".into());
+            let (_, inner) = collect(|| line(|| "inner".into()));
+            line(|| "second".into());
+            inner
+        });
+
+        assert_eq!(result, ["inner"]);
+        assert_eq!(lines, ["first", "second"]);
+        assert!(!enabled());
+    }
+}
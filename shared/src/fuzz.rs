@@ -0,0 +1,158 @@
+//! Fuzz targets for the input parsers. Every day has a target that runs all of its parsers on arbitrary bytes,
+//! which must never panic whatever the bytes are. The fuzz subcommand feeds the targets mutated generated inputs.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::generate::{self, Rng};
+
+/// Runs every parser of a day on the bytes and ignores the results
+pub type Target = fn(&[u8]);
+
+/// Fuzz target of every day that has one
+pub fn target(day: u32) -> Option<Target>{
+    let target: Target = match day{
+        1 => crate::day1::fuzz,
+        2 => crate::day2::fuzz,
+        3 => crate::day3::fuzz,
+        4 => crate::day4::fuzz,
+        5 => crate::day5::fuzz,
+        6 => crate::day6::fuzz,
+        7 => crate::day7::fuzz,
+        8 => crate::day8::fuzz,
+        9 => crate::day9::fuzz,
+        10 => crate::day10::fuzz,
+        11 => crate::day11::fuzz,
+        _ => return None,
+    };
+    Some(target)
+}
+
+/// Bytes that the puzzle formats give a meaning to, mutations prefer them over any other byte
+const INTERESTING: &[u8] = b"0123456789 :;,|=()-.#*SLRJF7AZ\n";
+
+fn random_byte(rng: &mut Rng) -> u8{
+    if rng.chance(0.8){
+        *rng.choose(INTERESTING)
+    } else{
+        rng.below(256) as u8
+    }
+}
+
+/// Applies a few random edits to the input
+pub fn mutate(rng: &mut Rng, input: &mut Vec<u8>){
+    for _ in 0..rng.range(1..5){
+        let position = rng.range(0..input.len() + 1);
+        match rng.below(6){
+            0 if !input.is_empty() => {
+                let index = rng.range(0..input.len());
+                input[index] = random_byte(rng);
+            },
+            1 => input.insert(position, random_byte(rng)),
+            2 => {
+                let end = (position + rng.range(1..8)).min(input.len());
+                input.drain(position..end);
+            },
+            // Long runs of digits overflow any integer type
+            3 => {
+                let digits = vec![b'9'; rng.range(10..45)];
+                input.splice(position..position, digits);
+            },
+            4 if !input.is_empty() => {
+                let start = rng.range(0..input.len());
+                let end = (start + rng.range(1..16)).min(input.len());
+                let copy = input[start..end].to_vec();
+                input.splice(position..position, copy);
+            },
+            _ => input.truncate(position),
+        }
+    }
+}
+
+#[derive(clap::Args)]
+pub struct FuzzArgs{
+    /// Day to fuzz, every day when left out
+    #[arg(long)]
+    day: Option<u32>,
+
+    /// Number of inputs per day
+    #[arg(long, default_value_t = 10000)]
+    iterations: u64,
+
+    /// Seed of the random generator, the same seed gives the same inputs
+    #[arg(long, default_value_t = 1)]
+    seed: u64,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum FuzzError{
+    #[error("there is no fuzz target for day {0}")]
+    UnknownDay(u32),
+    #[error("the parsers of day {day} panicked on the input {:?}", String::from_utf8_lossy(.input))]
+    Panic{ day: u32, input: Vec<u8> },
+}
+
+/// Feeds a day's target mutated generated inputs, returning the input it panicked on
+pub fn fuzz_day(day: u32, iterations: u64, seed: u64) -> Result<(), FuzzError>{
+    let target = target(day).ok_or(FuzzError::UnknownDay(day))?;
+    let generator = generate::generator(day).ok_or(FuzzError::UnknownDay(day))?;
+
+    let mut rng = Rng::new(seed);
+    for _ in 0..iterations{
+        let size = rng.range(1..12);
+        let mut input = generator(&mut rng, size).into_bytes();
+        mutate(&mut rng, &mut input);
+
+        if catch_unwind(AssertUnwindSafe(|| target(&input))).is_err(){
+            return Err(FuzzError::Panic{ day, input });
+        }
+    }
+    Ok(())
+}
+
+pub fn fuzz(args: &FuzzArgs) -> Result<(), FuzzError>{
+    let days = match args.day{
+        Some(day) => vec![day],
+        None => (1..=11).collect(),
+    };
+
+    for day in days{
+        fuzz_day(day, args.iterations, args.seed)?;
+        println!("Day {}: {} inputs parsed without a panic", day, args.iterations);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests{
+    use proptest::prelude::*;
+    use crate::generate::Rng;
+    use super::*;
+
+    #[test]
+    fn test_mutations_never_panic(){
+        for day in 1..=11{
+            if let Err(error) = fuzz_day(day, 500, day as u64){
+                panic!("{}", error);
+            }
+        }
+        assert!(matches!(fuzz_day(12, 1, 1), Err(FuzzError::UnknownDay(12))));
+    }
+
+    #[test]
+    fn test_mutate_deterministic(){
+        let mutated = |seed|{
+            let mut input = b"Game 1: 3 blue".to_vec();
+            mutate(&mut Rng::new(seed), &mut input);
+            input
+        };
+        assert_eq!(mutated(4), mutated(4));
+        assert!((0..20).any(|seed| mutated(seed) != b"Game 1: 3 blue"));
+    }
+
+    proptest!{
+        #[test]
+        fn test_arbitrary_bytes_never_panic(day in 1u32..=11, data in prop::collection::vec(any::<u8>(), 0..300)){
+            target(day).unwrap()(&data);
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! Seeded random puzzle inputs, for stress tests and for inputs larger than the samples
+
+use std::path::PathBuf;
+
+/// Small deterministic random number generator (SplitMix64), the same seed always gives the same input
+#[derive(Debug, Clone)]
+pub struct Rng{
+    state: u64,
+}
+
+impl Rng{
+    pub fn new(seed: u64) -> Rng{
+        Rng{ state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64{
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut value = self.state;
+        value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        value ^ (value >> 31)
+    }
+
+    /// Uniform value in 0..bound, bound must not be 0
+    pub fn below(&mut self, bound: u64) -> u64{
+        // Multiply and shift instead of a modulo, which is less biased for large bounds
+        ((self.next_u64() as u128 * bound as u128) >> 64) as u64
+    }
+
+    /// Uniform value in the range, which must not be empty
+    pub fn range(&mut self, range: std::ops::Range<usize>) -> usize{
+        range.start + self.below((range.end - range.start) as u64) as usize
+    }
+
+    /// True with the given probability
+    pub fn chance(&mut self, probability: f64) -> bool{
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64 <= probability
+    }
+
+    /// Random element of a slice, which must not be empty
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T{
+        &items[self.range(0..items.len())]
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]){
+        for index in (1..items.len()).rev(){
+            items.swap(index, self.range(0..index + 1));
+        }
+    }
+}
+
+/// Generates an input of roughly the given size, what the size means depends on the day
+pub type Generator = fn(&mut Rng, usize) -> String;
+
+/// Generator of every day that has one
+pub fn generator(day: u32) -> Option<Generator>{
+    let generator: Generator = match day{
+        1 => crate::day1::generate,
+        2 => crate::day2::generate,
+        3 => crate::day3::generate,
+        4 => crate::day4::generate,
+        5 => crate::day5::generate,
+        6 => crate::day6::generate,
+        7 => crate::day7::generate,
+        8 => crate::day8::generate,
+        9 => crate::day9::generate,
+        10 => crate::day10::generate,
+        11 => crate::day11::generate,
+        _ => return None,
+    };
+    Some(generator)
+}
+
+#[derive(clap::Args)]
+pub struct GenerateArgs{
+    /// Day to generate an input for
+    #[arg(long)]
+    day: u32,
+
+    /// Seed of the random generator, the same seed gives the same input
+    #[arg(long, default_value_t = 1)]
+    seed: u64,
+
+    /// Size of the input, such as the number of lines or the width of a grid
+    #[arg(long, default_value_t = 100)]
+    size: usize,
+
+    /// File to write the input to instead of standard output
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GenerateError{
+    #[error("there is no generator for day {0}")]
+    UnknownDay(u32),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+pub fn generate(args: &GenerateArgs) -> Result<(), GenerateError>{
+    let generator = generator(args.day).ok_or(GenerateError::UnknownDay(args.day))?;
+    let input = generator(&mut Rng::new(args.seed), args.size);
+
+    match &args.output{
+        Some(path) => std::fs::write(path, input)?,
+        None => print!("{}", input),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn test_rng_deterministic(){
+        let values = |seed| { let mut rng = Rng::new(seed); (0..10).map(|_| rng.next_u64()).collect::<Vec<_>>() };
+        assert_eq!(values(5), values(5));
+        assert_ne!(values(5), values(6));
+    }
+
+    #[test]
+    fn test_rng_bounds(){
+        let mut rng = Rng::new(3);
+        for bound in 1..50{
+            assert!(rng.below(bound) < bound);
+            assert!((10..10 + bound as usize).contains(&rng.range(10..10 + bound as usize)));
+        }
+
+        let mut items: Vec<u32> = (0..20).collect();
+        rng.shuffle(&mut items);
+        items.sort();
+        assert_eq!(items, (0..20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_every_day_generates(){
+        for day in 1..=11{
+            let generator = generator(day).unwrap();
+            let input = generator(&mut Rng::new(day as u64), 20);
+            assert!(!input.is_empty());
+            assert_eq!(input, generator(&mut Rng::new(day as u64), 20));
+        }
+        assert!(generator(12).is_none());
+    }
+}
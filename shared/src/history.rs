@@ -0,0 +1,247 @@
+//! Answers and timings of every run, appended one JSON object per task to a file that the history command
+//! reads back. An answer that stayed the same over several runs and then changes is most likely a
+//! regression, so those changes are flagged.
+
+use std::{io::Write, path::{Path, PathBuf}, time::{SystemTime, UNIX_EPOCH}};
+
+use serde::{Deserialize, Serialize};
+
+use crate::alloc::AllocStats;
+
+/// Where the runs are recorded
+const HISTORY_FILE: &str = "target/aoc-history/runs.jsonl";
+
+/// Runs with the same answer after which the answer counts as stable
+const STABLE_RUNS: usize = 3;
+
+/// A task's run on one input
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Record{
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    /// Commit the runner was built from as git describe puts it, None when it was built outside of a git checkout
+    pub commit: Option<String>,
+    pub task: String,
+    /// Path of the input
+    pub input: String,
+    pub answer: String,
+    pub micros: u128,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allocations: Option<AllocStats>,
+}
+
+impl Record{
+    pub fn new(task: String, input: &Path, output: &[u8], micros: u128, allocations: Option<AllocStats>) -> Record{
+        Record{
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs()),
+            commit: None,
+            task,
+            input: input.display().to_string(),
+            answer: String::from_utf8_lossy(output).trim_end().to_string(),
+            micros,
+            allocations,
+        }
+    }
+}
+
+/// The commit the runner was built from, marked dirty when it was built with uncommitted changes.
+/// The build script captures it, asking git at run time would blame edits made since on the wrong build.
+pub fn build_commit() -> Option<&'static str>{
+    option_env!("AOC_COMMIT")
+}
+
+fn append_to(path: &Path, records: &[Record]) -> std::io::Result<()>{
+    if let Some(directory) = path.parent(){
+        std::fs::create_dir_all(directory)?;
+    }
+    let mut lines = Vec::new();
+    for record in records{
+        serde_json::to_writer(&mut lines, record)?;
+        lines.push(b'\n');
+    }
+    // A single write, so that runners finishing at the same time do not interleave their records
+    std::fs::OpenOptions::new().create(true).append(true).open(path)?.write_all(&lines)
+}
+
+/// Records the runs, filed under the commit the runner was built from
+pub fn append(mut records: Vec<Record>) -> std::io::Result<()>{
+    for record in &mut records{
+        record.commit = build_commit().map(String::from);
+    }
+    append_to(Path::new(HISTORY_FILE), &records)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum HistoryError{
+    #[error("line {line} of {} is not a run: {source}", .path.display())]
+    BadRecord{ path: PathBuf, line: usize, source: serde_json::Error },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+fn load(path: &Path) -> Result<Vec<Record>, HistoryError>{
+    let text = match std::fs::read_to_string(path){
+        Ok(text) => text,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error.into()),
+    };
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| serde_json::from_str(line).map_err(|source| HistoryError::BadRecord{ path: path.to_path_buf(), line: index + 1, source }))
+        .collect()
+}
+
+/// How an answer differs from the answer of the run before
+#[derive(Debug, PartialEq, Eq)]
+enum Change<'a>{
+    Same,
+    Changed,
+    /// Changed after the previous answer came out of this many runs in a row
+    Unstable{ previous: &'a str, runs: usize },
+}
+
+/// Changes of the answers of consecutive runs of a task on one input
+fn changes<'a>(records: &[&'a Record]) -> Vec<Change<'a>>{
+    let mut changes = Vec::with_capacity(records.len());
+    let mut runs = 0;
+    for (index, record) in records.iter().enumerate(){
+        let previous = index.checked_sub(1).map(|index| records[index].answer.as_str());
+        changes.push(match previous{
+            None => Change::Same,
+            Some(previous) if previous == record.answer => Change::Same,
+            Some(previous) if runs >= STABLE_RUNS => Change::Unstable{ previous, runs },
+            Some(_) => Change::Changed,
+        });
+        runs = if changes.last() == Some(&Change::Same) { runs + 1 } else { 1 };
+    }
+    changes
+}
+
+/// Date and time of a Unix timestamp in UTC
+fn format_timestamp(timestamp: u64) -> String{
+    let (days, seconds) = (timestamp / 86400, timestamp % 86400);
+    // Days to a civil date, after Howard Hinnant's days_from_civil inverse, with eras starting at March 1st
+    let shifted = days as i64 + 719468;
+    let era = shifted.div_euclid(146097);
+    let day_of_era = shifted.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+#[derive(clap::Args)]
+pub struct HistoryArgs{
+    /// Task to show, like 2023-5-1, every task when left out
+    task: Option<String>,
+
+    /// Only show the runs on this input
+    #[arg(long)]
+    input: Option<String>,
+
+    /// Most recent runs to show of every task and input
+    #[arg(long, default_value_t = 20)]
+    last: usize,
+
+    /// Only show the answers that changed after being stable
+    #[arg(long)]
+    unstable: bool,
+}
+
+pub fn history(args: &HistoryArgs) -> Result<(), HistoryError>{
+    let records = load(Path::new(HISTORY_FILE))?;
+    if records.is_empty(){
+        println!("No runs recorded yet");
+        return Ok(());
+    }
+
+    let mut keys: Vec<(&str, &str)> = records.iter()
+        .filter(|record| args.task.as_ref().is_none_or(|task| record.task == *task) && args.input.as_ref().is_none_or(|input| record.input == *input))
+        .map(|record| (record.task.as_str(), record.input.as_str()))
+        .collect();
+    keys.sort_unstable();
+    keys.dedup();
+
+    let mut unstable = 0;
+    for (task, input) in keys{
+        let runs: Vec<&Record> = records.iter().filter(|record| record.task == task && record.input == input).collect();
+        let changes = changes(&runs);
+        unstable += changes.iter().filter(|change| matches!(change, Change::Unstable{ .. })).count();
+
+        let shown: Vec<_> = runs.iter().zip(&changes).skip(runs.len().saturating_sub(args.last))
+            .filter(|(_, change)| !args.unstable || matches!(change, Change::Unstable{ .. }))
+            .collect();
+        if shown.is_empty(){
+            continue;
+        }
+
+        println!("{} on {}", task, input);
+        for (record, change) in shown{
+            let commit = record.commit.as_deref().unwrap_or("-");
+            print!("  {}  {:<20} {:>10}mcs  {}", format_timestamp(record.timestamp), commit, record.micros, record.answer);
+            match change{
+                Change::Same => println!(),
+                Change::Changed => println!("  (changed)"),
+                Change::Unstable{ previous, runs } => println!("  ! changed after {} runs of {}", runs, previous),
+            }
+        }
+    }
+
+    if unstable > 0{
+        println!("{} answers changed after being stable", unstable);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn record(answer: &str) -> Record{
+        Record{ timestamp: 0, commit: Some("abc1234".into()), task: "2023-5-1".into(), input: "inputs/2023/5.txt".into(), answer: answer.into(), micros: 10, allocations: None }
+    }
+
+    #[test]
+    fn test_changes(){
+        let records: Vec<Record> = ["1", "1", "2", "2", "2", "3", "3", "4"].into_iter().map(record).collect();
+        let records: Vec<&Record> = records.iter().collect();
+        assert_eq!(changes(&records), [
+            Change::Same, Change::Same, Change::Changed, Change::Same, Change::Same,
+            Change::Unstable{ previous: "2", runs: 3 }, Change::Same, Change::Changed,
+        ]);
+    }
+
+    #[test]
+    fn test_format_timestamp(){
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00");
+        assert_eq!(format_timestamp(951782400), "2000-02-29 00:00:00");
+        assert_eq!(format_timestamp(1701388800 + 3661), "2023-12-01 01:01:01");
+    }
+
+    #[test]
+    fn test_append_and_load(){
+        let path = std::env::temp_dir().join(format!("aoc_history_test_{}", std::process::id())).join("runs.jsonl");
+        assert_eq!(load(&path).unwrap(), []);
+
+        let mut counted = record("Result: 35");
+        counted.allocations = Some(AllocStats{ allocations: 1, bytes_allocated: 8, peak_bytes: 8 });
+        append_to(&path, &[record("Result: 35")]).unwrap();
+        append_to(&path, std::slice::from_ref(&counted)).unwrap();
+        assert_eq!(load(&path).unwrap(), [record("Result: 35"), counted]);
+
+        std::fs::write(&path, "{}\n").unwrap();
+        assert!(matches!(load(&path), Err(HistoryError::BadRecord{ line: 1, .. })));
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_record_answer(){
+        let record = Record::new("2023-5-1".into(), Path::new("inputs/2023/5.txt"), b"Result: 35\n", 12, None);
+        assert_eq!(record.answer, "Result: 35");
+        assert_eq!(record.input, "inputs/2023/5.txt");
+    }
+}
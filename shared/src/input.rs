@@ -0,0 +1,95 @@
+//! Borrowed views of a puzzle input. The runner reads every input into memory once and tasks split it
+//! in place, instead of reading it through a BufRead that allocates a String for every line.
+
+use std::str::FromStr;
+
+/// Lines of a buffer without their line endings, the byte counterpart of str::lines
+pub fn lines(data: &[u8]) -> Lines<'_>{
+    Lines{ rest: data }
+}
+
+pub struct Lines<'a>{
+    rest: &'a [u8],
+}
+
+impl<'a> Iterator for Lines<'a>{
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]>{
+        if self.rest.is_empty(){
+            return None;
+        }
+
+        match self.rest.iter().position(|byte| *byte == b'\n'){
+            Some(end) => {
+                let line = &self.rest[..end];
+                self.rest = &self.rest[end + 1..];
+                Some(line.strip_suffix(b"\r").unwrap_or(line))
+            },
+            None => Some(std::mem::take(&mut self.rest)),
+        }
+    }
+}
+
+/// Fields of a line separated by runs of ASCII whitespace, the byte counterpart of str::split_ascii_whitespace
+pub fn fields(line: &[u8]) -> impl Iterator<Item = &[u8]>{
+    line.split(u8::is_ascii_whitespace).filter(|field| !field.is_empty())
+}
+
+/// Parses a field without copying it, None when it is not valid UTF-8 or does not parse
+pub fn parse<T: FromStr>(field: &[u8]) -> Option<T>{
+    std::str::from_utf8(field).ok()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests{
+    use proptest::prelude::*;
+    use super::*;
+
+    #[test]
+    fn test_lines(){
+        assert_eq!(lines(b"a\nbc\r\n\nd").collect::<Vec<_>>(), [&b"a"[..], b"bc", b"", b"d"]);
+        assert_eq!(lines(b"a\n").collect::<Vec<_>>(), [b"a"]);
+        assert_eq!(lines(b"").count(), 0);
+        assert_eq!(lines(b"\n").collect::<Vec<_>>(), [b""]);
+    }
+
+    #[test]
+    fn test_fields(){
+        assert_eq!(fields(b"  12 \t-3  x ").collect::<Vec<_>>(), [&b"12"[..], b"-3", b"x"]);
+        assert_eq!(fields(b"  ").count(), 0);
+        assert_eq!(parse::<i32>(b"-3"), Some(-3));
+        assert_eq!(parse::<u8>(b"256"), None);
+        assert_eq!(parse::<u8>(b"\xff"), None);
+    }
+
+    #[test]
+    fn test_task_adapters(){
+        let answer = |day, input: &[u8]|{
+            let task = crate::AOC_ENTRIES.iter().find(|entry| entry.day() == day && entry.part() == 1 && entry.variant().is_none()).unwrap();
+            let mut output = Vec::new();
+            task.invoke(input, &mut output).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        // Day 6 still reads through a BufRead, day 8 takes text and day 11 bytes
+        assert_eq!(answer(6, b"Time: 7 15 30\nDistance: 9 40 200\n"), "Result: 288\n");
+        assert_eq!(answer(8, b"LLR\n\nAAA = (BBB, BBB)\nBBB = (AAA, ZZZ)\nZZZ = (ZZZ, ZZZ)\n"), "Result: 6\n");
+        assert!(answer(8, b"LLR\n\n\xff").starts_with("Error: invalid utf-8"));
+        assert_eq!(answer(11, b"#.\n.#\n"), "Result: 2\n");
+    }
+
+    proptest!{
+        #[test]
+        fn test_lines_match_str_lines(text in "[ab\r\n]{0,20}"){
+            let expected: Vec<&[u8]> = text.lines().map(str::as_bytes).collect();
+            prop_assert_eq!(lines(text.as_bytes()).collect::<Vec<_>>(), expected);
+        }
+
+        #[test]
+        fn test_fields_match_split_ascii_whitespace(text in "[a1 \t]{0,20}"){
+            let expected: Vec<&[u8]> = text.split_ascii_whitespace().map(str::as_bytes).collect();
+            prop_assert_eq!(fields(text.as_bytes()).collect::<Vec<_>>(), expected);
+        }
+    }
+}
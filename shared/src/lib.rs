@@ -0,0 +1,148 @@
+//! Solutions of Advent of Code 2023 with the tooling around them. Every part registers itself in
+//! AOC_ENTRIES through the aoc_task attribute, and each day module exposes its parsers and solvers.
+//! The runner binary is a command line frontend over this crate.
+
+use std::io::Write;
+pub use linkme;
+
+pub mod day1;
+pub mod day2;
+pub mod day3;
+pub mod day4;
+pub mod day5;
+pub mod day6;
+pub mod day7;
+pub mod day8;
+pub mod day9;
+pub mod day10;
+pub mod day11;
+
+pub mod math;
+pub mod visualize;
+pub mod explain;
+pub mod input;
+pub mod generate;
+pub mod differential;
+pub mod fuzz;
+pub mod normalize;
+pub mod bench;
+pub mod alloc;
+pub mod watch;
+pub mod history;
+pub mod phases;
+
+/// The runner installs the counting allocator itself, unit tests of the allocation accounting need it as well
+#[cfg(test)]
+#[global_allocator]
+static ALLOCATOR: alloc::CountingAllocator = alloc::CountingAllocator;
+
+pub trait AocTask{
+    fn year(&self) -> u32;
+    fn day(&self) -> u32;
+    fn part(&self) -> u32;
+    /// Name of an alternative implementation of the same part, None for the default one
+    fn variant(&self) -> Option<&'static str>;
+    /// Solves the part for the whole input, which the runner has already read into memory
+    fn invoke(&self, input: &[u8], output: &mut dyn Write) -> std::io::Result<()>;
+    /// Parse and solve phases of the part, None when it was not registered with them
+    fn phases(&self) -> Option<phases::Parse>{
+        None
+    }
+}
+
+#[linkme::distributed_slice]
+pub static AOC_ENTRIES: [&(dyn AocTask + Sync)];
+
+
+pub trait AocResult{
+    fn write(&self, write: &mut dyn std::io::Write) -> std::io::Result<()>;
+}
+
+impl<T: std::fmt::Display, E: std::fmt::Display> AocResult for Result<T, E>{
+    fn write(&self, write: &mut dyn std::io::Write) -> std::io::Result<()>{
+        match self{
+            Ok(value) => writeln!(write, "Result: {}", value),
+            Err(error) => writeln!(write, "Error: {}", error),
+        }
+    }
+}
+
+impl<T: std::fmt::Display> AocResult for Option<T>{
+    fn write(&self, write: &mut dyn std::io::Write) -> std::io::Result<()>{
+        match self{
+            Some(value) => writeln!(write, "Result: {}", value),
+            None => writeln!(write, "Error"),
+        }
+    }
+}
+
+macro_rules! aoc_result_display {
+    ($name:ident) => {
+        impl AocResult for $name{
+            fn write(&self, write: &mut dyn std::io::Write) -> std::io::Result<()>{
+                writeln!(write, "Result: {}", self)
+            }
+        }
+    };
+}
+
+aoc_result_display!(u32);
+aoc_result_display!(usize);
+aoc_result_display!(u128);
+
+
+pub fn run<R: AocResult>(input: R, output: &mut dyn Write) -> std::io::Result<()>{
+    input.write(output)
+}
+
+/// Name of a task that is safe to use as a file name
+pub fn task_name(entry: &dyn AocTask) -> String{
+    match entry.variant(){
+        Some(variant) => format!("{}-{}-{}-{}", entry.year(), entry.day(), entry.part(), variant),
+        None => format!("{}-{}-{}", entry.year(), entry.day(), entry.part()),
+    }
+}
+
+/// What the runner prints for a task with --json
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct TaskReport{
+    pub task: String,
+    pub year: u32,
+    pub day: u32,
+    pub part: u32,
+    pub variant: Option<String>,
+    pub output: String,
+    #[serde(default)]
+    pub explanation: Vec<String>,
+    pub micros: u128,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allocations: Option<alloc::AllocStats>,
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn test_registry(){
+        let mut names: Vec<String> = AOC_ENTRIES.iter().map(|entry| task_name(*entry)).collect();
+        for day in 1..=11{
+            for part in 1..=2{
+                assert!(names.contains(&format!("2023-{}-{}", day, part)), "day {} part {}", day, part);
+            }
+        }
+        let count = names.len();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), count);
+    }
+
+    #[test]
+    fn test_task_report_round_trip(){
+        let text = r#"{"task":"2023-6-1","year":2023,"day":6,"part":1,"variant":null,"output":"Result: 288\n","micros":12}"#;
+        let report: TaskReport = serde_json::from_str(text).unwrap();
+        assert_eq!((report.task.as_str(), report.output.as_str(), report.allocations), ("2023-6-1", "Result: 288\n", None));
+        assert!(report.explanation.is_empty());
+        assert_eq!(serde_json::from_str::<TaskReport>(&serde_json::to_string(&report).unwrap()).unwrap().micros, 12);
+    }
+}
@@ -1,97 +1,276 @@
+use std::io::Write;
+use clap::{Parser, Subcommand};
 
-use std::{io::{BufRead, Cursor, BufReader}, fs::File, fmt::Display};
-pub use linkme;
-
-mod day1;
-mod day2;
-mod day3;
-mod day4;
-mod day5;
-mod day6;
-mod day7;
-mod day8;
-mod day9;
-mod day10;
-mod day11;
-
-pub trait AocTask{
-    fn year(&self) -> u32;
-    fn day(&self) -> u32;
-    fn part(&self) -> u32;
-    fn invoke(&self, reader: &mut dyn BufRead);
-}
+use aoc_2023_shared::{alloc, bench, day10, differential, explain, fuzz, generate, history, normalize, task_name, visualize, watch, AocTask, TaskReport, AOC_ENTRIES};
+
+/// Only counts while a task is measured, see alloc::measure
+#[global_allocator]
+static ALLOCATOR: alloc::CountingAllocator = alloc::CountingAllocator;
+
+#[derive(Parser)]
+struct Cli{
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Variant to run for parts that have alternative implementations
+    #[arg(long)]
+    variant: Option<String>,
+
+    /// Run every variant of a part and check that their results agree, including the slow references with --variant reference
+    #[arg(long)]
+    cross_check: bool,
+
+    /// Show the frames tasks emit while they run, either "terminal" for playback or a directory to write them to
+    #[arg(long, value_name = "TARGET", value_parser = parse_visualize_target)]
+    visualize: Option<VisualizeTarget>,
 
-#[linkme::distributed_slice]
-pub static AOC_ENTRIES: [&(dyn AocTask + Sync)];
+    /// Print the explanations tasks give for their results
+    #[arg(long)]
+    explain: bool,
 
+    /// Time between frames during terminal playback, in milliseconds
+    #[arg(long, default_value_t = 50)]
+    frame_delay: u64,
 
-pub trait AocResult{
-    fn write(&self, write: &mut dyn std::io::Write) -> std::io::Result<()>;
+    /// Count the allocations of every task and the most memory it had allocated at once
+    #[arg(long)]
+    alloc_stats: bool,
+
+    /// Print one JSON object per task instead of text
+    #[arg(long)]
+    json: bool,
+
+    /// Only run the tasks of this day
+    #[arg(long)]
+    day: Option<u32>,
+
+    /// Input to run the tasks of the day on instead of their usual one
+    #[arg(long, requires = "day")]
+    input: Option<std::path::PathBuf>,
 }
 
-impl<T: std::fmt::Display, E: std::fmt::Display> AocResult for Result<T, E>{
-    fn write(&self, write: &mut dyn std::io::Write) -> std::io::Result<()>{
-        match self{
-            Ok(value) => writeln!(write, "Result: {}", value),
-            Err(error) => writeln!(write, "Error: {}", error),
+#[derive(Clone)]
+enum VisualizeTarget{
+    Terminal,
+    Directory(std::path::PathBuf),
+}
+
+fn parse_visualize_target(value: &str) -> Result<VisualizeTarget, std::convert::Infallible>{
+    Ok(match value{
+        "terminal" => VisualizeTarget::Terminal,
+        directory => VisualizeTarget::Directory(directory.into()),
+    })
+}
+
+impl Cli{
+    fn visualizer(&self) -> Option<Box<dyn visualize::Visualizer>>{
+        match self.visualize.as_ref()?{
+            VisualizeTarget::Terminal => Some(Box::new(visualize::TerminalPlayback{ delay: std::time::Duration::from_millis(self.frame_delay) })),
+            VisualizeTarget::Directory(directory) => Some(Box::new(visualize::DirectoryWriter::new(directory.clone()))),
         }
     }
 }
 
-impl<T: std::fmt::Display> AocResult for Option<T>{
-    fn write(&self, write: &mut dyn std::io::Write) -> std::io::Result<()>{
-        match self{
-            Some(value) => writeln!(write, "Result: {}", value),
-            None => writeln!(write, "Error"),
-        }
+#[derive(Subcommand)]
+enum Command{
+    /// Run every registered task on its input (the default)
+    Run,
+    /// Draw the pipes of a day 10 map
+    RenderPipes(day10::render::RenderArgs),
+    /// Generate a random puzzle input
+    Generate(generate::GenerateArgs),
+    /// Compare the solutions with their reference implementations on generated inputs
+    Differential(differential::DifferentialArgs),
+    /// Feed the parsers mutated inputs and report the first one they panic on
+    Fuzz(fuzz::FuzzArgs),
+    /// Rewrite a puzzle input in its canonical format
+    Normalize(normalize::NormalizeArgs),
+    /// Time every task on its input over repeated runs, optionally against a saved baseline
+    Bench(bench::BenchArgs),
+    /// Rebuild and re-run the tasks of a day whenever its source or inputs change
+    Watch(watch::WatchArgs),
+    /// Show how the answers and times of the tasks changed over the recorded runs
+    History(history::HistoryArgs),
+}
+
+/// Picks the implementations of a single part that should run
+fn select_variants<'a>(cli: &Cli, variants: &[&'a (dyn AocTask + Sync)]) -> Vec<&'a (dyn AocTask + Sync)>{
+    if cli.cross_check{
+        let with_reference = cli.variant.as_deref() == Some(differential::REFERENCE);
+        return variants.iter().filter(|entry| with_reference || !differential::is_reference(**entry)).copied().collect();
     }
+
+    let requested = cli.variant.as_deref();
+    let selected = variants.iter().find(|entry| requested.is_some() && entry.variant() == requested)
+        .or_else(|| variants.iter().find(|entry| entry.variant().is_none()))
+        .or(variants.first());
+    selected.into_iter().copied().collect()
 }
 
-macro_rules! aoc_result_display {
-    ($name:ident) => {
-        impl AocResult for $name{
-            fn write(&self, write: &mut dyn std::io::Write) -> std::io::Result<()>{
-                writeln!(write, "Result: {}", self)
+fn main(){
+    let cli = Cli::parse();
+
+    match &cli.command{
+        None | Some(Command::Run) => run_tasks(&cli),
+        Some(Command::RenderPipes(args)) => {
+            if let Err(error) = day10::render::render(args){
+                eprintln!("Error: {}", error);
+                std::process::exit(1);
             }
-        }
-    };
+        },
+        Some(Command::Generate(args)) => {
+            if let Err(error) = generate::generate(args){
+                eprintln!("Error: {}", error);
+                std::process::exit(1);
+            }
+        },
+        Some(Command::Differential(args)) => {
+            if let Err(error) = differential::differential(args){
+                eprintln!("Error: {}", error);
+                std::process::exit(1);
+            }
+        },
+        Some(Command::Fuzz(args)) => {
+            if let Err(error) = fuzz::fuzz(args){
+                eprintln!("Error: {}", error);
+                std::process::exit(1);
+            }
+        },
+        Some(Command::Normalize(args)) => {
+            if let Err(error) = normalize::normalize(args){
+                eprintln!("Error: {}", error);
+                std::process::exit(1);
+            }
+        },
+        Some(Command::Bench(args)) => {
+            if let Err(error) = bench::bench(args){
+                eprintln!("Error: {}", error);
+                std::process::exit(1);
+            }
+        },
+        Some(Command::Watch(args)) => {
+            if let Err(error) = watch::watch(args){
+                eprintln!("Error: {}", error);
+                std::process::exit(1);
+            }
+        },
+        Some(Command::History(args)) => {
+            if let Err(error) = history::history(args){
+                eprintln!("Error: {}", error);
+                std::process::exit(1);
+            }
+        },
+    }
 }
 
-aoc_result_display!(u32);
-aoc_result_display!(usize);
+/// Runs f, collecting its explanation when the command line asks for one
+fn run_explained<T>(cli: &Cli, explanation: &mut Vec<String>, f: impl FnOnce() -> T) -> T{
+    if !cli.explain{
+        return f();
+    }
+
+    let (result, lines) = explain::collect(f);
+    *explanation = lines;
+    result
+}
 
+/// Runs f, counting its allocations when the command line asks for it
+fn run_counted<T>(cli: &Cli, allocations: &mut Option<alloc::AllocStats>, f: impl FnOnce() -> T) -> T{
+    if !cli.alloc_stats{
+        return f();
+    }
 
-pub fn run<R: AocResult>(input: R){
-    input.write(&mut std::io::stdout());
+    let (result, stats) = alloc::measure(f);
+    *allocations = stats;
+    result
 }
 
-fn main(){
+fn run_tasks(cli: &Cli){
+    let mut visualizer = cli.visualizer();
+
     // Order registered tasks by year, then day, then part
     let mut entries = AOC_ENTRIES.to_vec();
-    entries.sort_by(|a, b| {
-        if a.year() == b.year(){
-            if a.day() == b.day(){
-                a.part().cmp(&b.part())
+    entries.retain(|entry| cli.day.is_none_or(|day| entry.day() == day));
+    entries.sort_by_key(|entry| (entry.year(), entry.day(), entry.part(), entry.variant()));
+
+    let mut records = Vec::new();
+    for variants in entries.chunk_by(|a, b| (a.year(), a.day(), a.part()) == (b.year(), b.day(), b.part())){
+        let mut outputs = Vec::new();
+        for entry in select_variants(cli, variants){
+            if !cli.json{
+                match entry.variant(){
+                    Some(variant) => println!("{} day {} part {} ({})", entry.year(), entry.day(), entry.part(), variant),
+                    None => println!("{} day {} part {}", entry.year(), entry.day(), entry.part()),
+                }
+            }
+
+            let path = cli.input.clone().unwrap_or_else(|| format!("inputs/{}/{}.txt", entry.year(), entry.day()).into());
+            let input = std::fs::read(&path).unwrap();
+
+            let mut output = Vec::new();
+            let mut allocations = None;
+            let mut invoke = || run_counted(cli, &mut allocations, || entry.invoke(&input, &mut output));
+            let mut explanation = Vec::new();
+            let start = std::time::Instant::now();
+            let result = match visualizer.take(){
+                Some(mut active) => {
+                    active.begin(&task_name(entry));
+                    let (result, mut active) = visualize::with_visualizer(active, || run_explained(cli, &mut explanation, &mut invoke));
+                    if let Err(error) = active.finish(){
+                        eprintln!("Failed to visualize: {}", error);
+                    }
+                    visualizer = Some(active);
+                    result
+                },
+                None => run_explained(cli, &mut explanation, &mut invoke),
+            };
+            let end = std::time::Instant::now();
+            result.unwrap();
+            records.push(history::Record::new(task_name(entry), &path, &output, (end - start).as_micros(), allocations));
+
+            if cli.json{
+                let report = TaskReport{
+                    task: task_name(entry),
+                    year: entry.year(),
+                    day: entry.day(),
+                    part: entry.part(),
+                    variant: entry.variant().map(String::from),
+                    output: String::from_utf8_lossy(&output).into_owned(),
+                    explanation,
+                    micros: (end - start).as_micros(),
+                    allocations,
+                };
+                println!("{}", serde_json::to_string(&report).unwrap());
             }
             else{
-                a.day().cmp(&b.day())
+                for line in explanation{
+                    println!("  {}", line);
+                }
+                std::io::stdout().write_all(&output).unwrap();
+                println!("Took {}mcs", (end - start).as_micros());
+                if let Some(allocations) = allocations{
+                    println!("{}", allocations);
+                }
             }
+            outputs.push((entry.variant().unwrap_or("default"), output));
         }
-        else{
-            a.year().cmp(&b.year())
-        }
-    });
-
-    for entry in entries{
-        println!("{} day {} part {}", entry.year(), entry.day(), entry.part());
 
-        let path = format!("inputs/{}/{}.txt", entry.year(), entry.day());
-        let mut reader =  File::open(path).map(|file| BufReader::new(file)).unwrap();
-
-        let start = std::time::Instant::now();
-        entry.invoke(&mut reader);
-        let end = std::time::Instant::now();
+        if let Some(((first_variant, first_output), rest)) = outputs.split_first(){
+            for (variant, output) in rest{
+                if output != first_output{
+                    // Keeps standard output a stream of JSON objects
+                    if cli.json{
+                        eprintln!("Mismatch: {} and {} disagree", first_variant, variant);
+                    }
+                    else{
+                        println!("Mismatch: {} and {} disagree", first_variant, variant);
+                    }
+                }
+            }
+        }
+    }
 
-        println!("Took {}mcs", (end - start).as_micros());
+    if let Err(error) = history::append(records){
+        eprintln!("Failed to record the runs: {}", error);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,294 @@
+use num::{CheckedMul, Integer};
+use thiserror::Error;
+
+/// Greatest common divisor of all values, None when there are no values
+pub fn gcd_all<T, I>(values: I) -> Option<T>
+where
+    T: Integer + Copy,
+    I: IntoIterator<Item = T>,
+{
+    values.into_iter().reduce(|a, b| a.gcd(&b))
+}
+
+/// Least common multiple of two values, None when the result does not fit in T
+pub fn checked_lcm<T: Integer + CheckedMul + Copy>(a: T, b: T) -> Option<T> {
+    if a.is_zero() || b.is_zero() {
+        return Some(T::zero());
+    }
+
+    (a / a.gcd(&b)).checked_mul(&b)
+}
+
+/// Least common multiple of all values.
+/// None when there are no values, or when the result does not fit in T
+pub fn lcm_all<T, I>(values: I) -> Option<T>
+where
+    T: Integer + CheckedMul + Copy,
+    I: IntoIterator<Item = T>,
+{
+    let mut values = values.into_iter();
+    let first = values.next()?;
+    values.try_fold(first, checked_lcm)
+}
+
+/// Result of the extended euclidean algorithm: gcd = a * x + b * y
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ExtendedGcd {
+    pub gcd: i128,
+    pub x: i128,
+    pub y: i128,
+}
+
+/// Extended euclidean algorithm, the returned gcd is never negative
+pub fn extended_gcd(a: i128, b: i128) -> ExtendedGcd {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x) = (1, 0);
+    let (mut old_y, mut y) = (0, 1);
+
+    while r != 0 {
+        let quotient = old_r / r;
+        (old_r, r) = (r, old_r - quotient * r);
+        (old_x, x) = (x, old_x - quotient * x);
+        (old_y, y) = (y, old_y - quotient * y);
+    }
+
+    if old_r < 0 {
+        ExtendedGcd { gcd: -old_r, x: -old_x, y: -old_y }
+    } else {
+        ExtendedGcd { gcd: old_r, x: old_x, y: old_y }
+    }
+}
+
+/// A congruence value = residue (mod modulus), with the residue normalized to 0..modulus
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Congruence {
+    pub residue: i128,
+    pub modulus: i128,
+}
+
+impl Congruence {
+    /// Creates a congruence, returns None for non-positive moduli
+    pub fn new(residue: i128, modulus: i128) -> Option<Congruence> {
+        if modulus <= 0 {
+            return None;
+        }
+
+        Some(Congruence {
+            residue: residue.rem_euclid(modulus),
+            modulus,
+        })
+    }
+
+    /// Whether the given value satisfies this congruence
+    pub fn contains(&self, value: i128) -> bool {
+        value.rem_euclid(self.modulus) == self.residue
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum CrtError {
+    #[error("the congruences have no common solution")]
+    NoSolution,
+    #[error("the combined modulus does not fit in 128 bits")]
+    Overflow,
+}
+
+/// Combines two congruences into one that is satisfied exactly when both are.
+/// The moduli do not have to be coprime.
+fn combine_congruences(a: Congruence, b: Congruence) -> Result<Congruence, CrtError> {
+    let ExtendedGcd { gcd, x, .. } = extended_gcd(a.modulus, b.modulus);
+
+    let difference = b.residue - a.residue;
+    if difference % gcd != 0 {
+        return Err(CrtError::NoSolution);
+    }
+
+    // Solve a.modulus * k = difference (mod b.modulus), x is the inverse of a.modulus / gcd
+    let reduced_modulus = b.modulus / gcd;
+    let k = (difference / gcd)
+        .rem_euclid(reduced_modulus)
+        .checked_mul(x.rem_euclid(reduced_modulus))
+        .ok_or(CrtError::Overflow)?
+        .rem_euclid(reduced_modulus);
+
+    let modulus = a.modulus.checked_mul(reduced_modulus).ok_or(CrtError::Overflow)?;
+    let residue = a
+        .modulus
+        .checked_mul(k)
+        .and_then(|offset| offset.checked_add(a.residue))
+        .ok_or(CrtError::Overflow)?;
+
+    Ok(Congruence {
+        residue: residue.rem_euclid(modulus),
+        modulus,
+    })
+}
+
+/// Chinese remainder theorem over any number of congruences with possibly non-coprime moduli.
+/// An empty set of congruences results in 0 (mod 1), which every value satisfies.
+pub fn crt<I: IntoIterator<Item = Congruence>>(congruences: I) -> Result<Congruence, CrtError> {
+    congruences
+        .into_iter()
+        .try_fold(Congruence { residue: 0, modulus: 1 }, combine_congruences)
+}
+
+/// Largest integer whose square does not exceed value
+pub fn isqrt(value: u128) -> u128 {
+    if value < 2 {
+        return value;
+    }
+
+    // Newton's method on integers, starting from a power of two at or above the root. By the AM-GM
+    // inequality every step stays at or above the root, and each one decreases until the root is reached.
+    // From within a factor of two of the root the error squares every step, so 128 bits take a few steps.
+    let bits = 128 - value.leading_zeros();
+    let mut root = 1u128 << bits.div_ceil(2);
+    loop {
+        // Both terms stay below 2^65, so the sum does not overflow
+        let next = (root + value / root) / 2;
+        if next >= root {
+            return root;
+        }
+        root = next;
+    }
+}
+
+/// Counts the integers x for which x² - b·x + c < 0, or equivalently x·(b - x) > c.
+/// None when the discriminant does not fit in 128 bits.
+pub fn count_quadratic_below_zero(b: u128, c: u128) -> Option<u128> {
+    let discriminant = b.checked_mul(b)?.checked_sub(c.checked_mul(4)?);
+    let discriminant = match discriminant {
+        Some(discriminant) if discriminant > 0 => discriminant,
+        // No roots, or a single root where the quadratic touches zero
+        _ => return Some(0),
+    };
+
+    // The solutions are symmetric around b / 2 and all lie within 0..=b
+    let beats = |x: u128| x * (b - x) > c;
+    let mut first = (b - isqrt(discriminant).min(b)) / 2;
+    while first > 0 && beats(first - 1) {
+        first -= 1;
+    }
+    while first <= b / 2 && !beats(first) {
+        first += 1;
+    }
+
+    if first > b / 2 {
+        return Some(0);
+    }
+
+    let last = b - first;
+    Some(last - first + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gcd_all() {
+        assert_eq!(gcd_all([12u64, 18, 30]), Some(6));
+        assert_eq!(gcd_all([7u64]), Some(7));
+        assert_eq!(gcd_all(Vec::<u64>::new()), None);
+    }
+
+    #[test]
+    fn test_lcm_all() {
+        assert_eq!(lcm_all([2usize, 3, 4]), Some(12));
+        assert_eq!(lcm_all([0usize, 3]), Some(0));
+        assert_eq!(lcm_all(Vec::<usize>::new()), None);
+        assert_eq!(lcm_all([u64::MAX, u64::MAX - 1]), None);
+    }
+
+    #[test]
+    fn test_extended_gcd() {
+        for (a, b) in [(240, 46), (46, 240), (-12, 18), (7, 0), (0, 0), (17, 5)] {
+            let result = extended_gcd(a, b);
+            assert_eq!(result.gcd, num::Integer::gcd(&a, &b));
+            assert_eq!(a * result.x + b * result.y, result.gcd);
+        }
+    }
+
+    #[test]
+    fn test_crt_coprime() {
+        let congruences = [(2, 3), (3, 5), (2, 7)].map(|(r, m)| Congruence::new(r, m).unwrap());
+        assert_eq!(crt(congruences), Ok(Congruence { residue: 23, modulus: 105 }));
+    }
+
+    #[test]
+    fn test_crt_non_coprime() {
+        let congruences = [(3, 4), (5, 6)].map(|(r, m)| Congruence::new(r, m).unwrap());
+        assert_eq!(crt(congruences), Ok(Congruence { residue: 11, modulus: 12 }));
+
+        let congruences = [(0, 4), (1, 6)].map(|(r, m)| Congruence::new(r, m).unwrap());
+        assert_eq!(crt(congruences), Err(CrtError::NoSolution));
+    }
+
+    #[test]
+    fn test_crt_exhaustive_small() {
+        for m1 in 1..12 {
+            for m2 in 1..12 {
+                for r1 in 0..m1 {
+                    for r2 in 0..m2 {
+                        let a = Congruence::new(r1, m1).unwrap();
+                        let b = Congruence::new(r2, m2).unwrap();
+                        let expected = (0..m1 * m2).find(|x| a.contains(*x) && b.contains(*x));
+                        match crt([a, b]) {
+                            Ok(result) => {
+                                assert_eq!(Some(result.residue), expected);
+                                assert_eq!(result.modulus, num::integer::lcm(m1, m2));
+                            }
+                            Err(error) => {
+                                assert_eq!(error, CrtError::NoSolution);
+                                assert_eq!(expected, None);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_crt_overflow() {
+        let a = Congruence::new(1, i128::MAX - 1).unwrap();
+        let b = Congruence::new(2, i128::MAX - 2).unwrap();
+        assert_eq!(crt([a, b]), Err(CrtError::Overflow));
+    }
+
+    #[test]
+    fn test_isqrt() {
+        for value in 0..10_000u128 {
+            let root = isqrt(value);
+            assert!(root * root <= value);
+            assert!((root + 1) * (root + 1) > value);
+        }
+
+        assert_eq!(isqrt(u128::MAX), u64::MAX as u128);
+        assert_eq!(isqrt((1u128 << 100) - 1), (1u128 << 50) - 1);
+        assert_eq!(isqrt(1u128 << 100), 1u128 << 50);
+
+        let square = |root: u128| root * root;
+        for root in [u64::MAX as u128, (1 << 63) + 12345, 3037000499, 1 << 32] {
+            assert_eq!(isqrt(square(root)), root);
+            assert_eq!(isqrt(square(root) - 1), root - 1);
+            assert_eq!(isqrt(square(root) + root), root);
+        }
+    }
+
+    #[test]
+    fn test_count_quadratic_below_zero() {
+        assert_eq!(count_quadratic_below_zero(7, 9), Some(4));
+        assert_eq!(count_quadratic_below_zero(15, 40), Some(8));
+        assert_eq!(count_quadratic_below_zero(30, 200), Some(9));
+
+        // Exact integer roots are not counted
+        assert_eq!(count_quadratic_below_zero(10, 21), Some(3));
+
+        // Touching and missing zero entirely
+        assert_eq!(count_quadratic_below_zero(10, 25), Some(0));
+        assert_eq!(count_quadratic_below_zero(10, 26), Some(0));
+
+        assert_eq!(count_quadratic_below_zero(u128::MAX, 0), None);
+    }
+}
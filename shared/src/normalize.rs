@@ -0,0 +1,94 @@
+//! Rewrites puzzle inputs in their canonical format: the input is parsed and its model displayed again.
+//! Canonical inputs parse to the same model as the original, so every answer stays the same.
+
+use std::path::PathBuf;
+
+/// Parses an input and displays the parsed model, None when the input does not parse
+pub type Normalizer = fn(&[u8]) -> Option<String>;
+
+/// Normalizer of every day whose parsed model can be displayed
+pub fn normalizer(day: u32) -> Option<Normalizer>{
+    let normalizer: Normalizer = match day{
+        2 => crate::day2::normalize,
+        3 => crate::day3::normalize,
+        4 => crate::day4::normalize,
+        5 => crate::day5::normalize,
+        6 => crate::day6::normalize,
+        7 => crate::day7::normalize,
+        8 => crate::day8::normalize,
+        10 => crate::day10::normalize,
+        11 => crate::day11::normalize,
+        _ => return None,
+    };
+    Some(normalizer)
+}
+
+#[derive(clap::Args)]
+pub struct NormalizeArgs{
+    /// Day of the input
+    #[arg(long)]
+    day: u32,
+
+    /// Input to rewrite, defaults to the input of the day
+    input: Option<PathBuf>,
+
+    /// File to write the canonical input to instead of standard output, which may be the input itself
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum NormalizeError{
+    #[error("there is no canonical format for day {0}")]
+    UnknownDay(u32),
+    #[error("{} is not a valid input for day {day}", .path.display())]
+    Parse{ day: u32, path: PathBuf },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+pub fn normalize(args: &NormalizeArgs) -> Result<(), NormalizeError>{
+    let normalizer = normalizer(args.day).ok_or(NormalizeError::UnknownDay(args.day))?;
+    let path = args.input.clone().unwrap_or_else(|| PathBuf::from(format!("inputs/2023/{}.txt", args.day)));
+    let input = std::fs::read(&path)?;
+    let normalized = normalizer(&input).ok_or(NormalizeError::Parse{ day: args.day, path })?;
+
+    match &args.output{
+        Some(path) => std::fs::write(path, normalized)?,
+        None => print!("{}", normalized),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::generate::{self, Rng};
+    use super::*;
+
+    #[test]
+    fn test_normalize_generated(){
+        for day in 1..=11{
+            let Some(normalizer) = normalizer(day) else { continue };
+            let input = generate::generator(day).unwrap()(&mut Rng::new(day as u64), 20);
+            let normalized = normalizer(input.as_bytes()).unwrap();
+            assert_eq!(normalizer(normalized.as_bytes()).as_ref(), Some(&normalized), "day {}", day);
+        }
+        assert!(normalizer(12).is_none());
+    }
+
+    #[test]
+    fn test_normalize_keeps_answers(){
+        for entry in crate::AOC_ENTRIES.iter().filter(|entry| entry.variant().is_none()){
+            let Some(normalizer) = normalizer(entry.day()) else { continue };
+            let input = generate::generator(entry.day()).unwrap()(&mut Rng::new(3), 10);
+            let normalized = normalizer(input.as_bytes()).unwrap();
+
+            let answer = |input: &[u8]|{
+                let mut output = Vec::new();
+                entry.invoke(input, &mut output).unwrap();
+                output
+            };
+            assert_eq!(answer(normalized.as_bytes()), answer(input.as_bytes()), "day {} part {}", entry.day(), entry.part());
+        }
+    }
+}
@@ -0,0 +1,72 @@
+//! Parts split into their parse and solve phases, so that benchmarks can time the two apart. Only parts that
+//! parse the whole input into a model before solving anything can be split, the others are timed as a whole.
+//! A part is split by naming its phases in its aoc_task attribute, `parse = ..., solve = ...`, and the
+//! registry hands them out through AocTask::phases.
+
+use std::io::Write;
+
+use crate::AocResult;
+
+/// Solves an already parsed input and writes the answer like the task does, every time it is called
+pub type Solve<'a> = Box<dyn FnMut(&mut dyn Write) -> std::io::Result<()> + 'a>;
+
+/// Parses an input and returns the solve phase for it, None when the input does not parse
+pub type Parse = for<'a> fn(&'a [u8]) -> Option<Solve<'a>>;
+
+/// Solve phase running f
+pub fn solve<'a, R: AocResult>(mut f: impl FnMut() -> R + 'a) -> Solve<'a>{
+    Box::new(move |output| crate::run(f(), output))
+}
+
+/// What a parser returns, parsers report failures either way
+pub trait Parsed<T>{
+    fn parsed(self) -> Option<T>;
+}
+
+impl<T> Parsed<T> for Option<T>{
+    fn parsed(self) -> Option<T>{
+        self
+    }
+}
+
+impl<T, E> Parsed<T> for Result<T, E>{
+    fn parsed(self) -> Option<T>{
+        self.ok()
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::{generate::{self, Rng}, AocTask, AOC_ENTRIES};
+
+    fn entry(day: u32, part: u32) -> &'static dyn AocTask{
+        *AOC_ENTRIES.iter().find(|entry| entry.day() == day && entry.part() == part && entry.variant().is_none()).unwrap()
+    }
+
+    #[test]
+    fn test_phases_match_tasks(){
+        let mut split = 0;
+        for entry in AOC_ENTRIES.iter(){
+            let Some(parse) = entry.phases() else { continue };
+            split += 1;
+            let input = generate::generator(entry.day()).unwrap()(&mut Rng::new(5), 10);
+            let mut expected = Vec::new();
+            entry.invoke(input.as_bytes(), &mut expected).unwrap();
+
+            let mut solve = parse(input.as_bytes()).unwrap_or_else(|| panic!("day {} part {}", entry.day(), entry.part()));
+            for _ in 0..2{
+                let mut output = Vec::new();
+                solve(&mut output).unwrap();
+                assert_eq!(String::from_utf8(output).unwrap(), String::from_utf8(expected.clone()).unwrap(), "day {} part {}", entry.day(), entry.part());
+            }
+        }
+        assert_eq!(split, 16);
+        assert!(entry(1, 1).phases().is_none());
+    }
+
+    #[test]
+    fn test_parse_failure(){
+        assert!(entry(5, 1).phases().unwrap()(b"not an almanac").is_none());
+        assert!(entry(8, 1).phases().unwrap()(b"LR\n\n\xff").is_none());
+    }
+}
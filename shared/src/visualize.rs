@@ -0,0 +1,257 @@
+//! Optional frames that tasks can emit to show what they are doing.
+//! Frames are only built while a visualizer is active, so emitting them costs a single check otherwise.
+
+use std::{cell::RefCell, fmt::Write as _, io::{IsTerminal, Write as _}, path::PathBuf, time::Duration};
+
+pub type Color = [u8; 3];
+
+pub const HIGHLIGHT: Color = [240, 200, 40];
+pub const GOOD: Color = [40, 200, 80];
+pub const BAD: Color = [230, 50, 50];
+pub const MUTED: Color = [90, 90, 90];
+
+/// A grid of characters with some of its cells coloured
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Frame{
+    pub caption: String,
+    pub rows: Vec<Vec<char>>,
+    /// Colour of individual cells as (x, y, colour), later entries win
+    pub highlights: Vec<(usize, usize, Color)>,
+}
+
+impl Frame{
+    pub fn new(caption: impl Into<String>) -> Frame{
+        Frame{ caption: caption.into(), ..Default::default() }
+    }
+
+    pub fn from_lines<I, S>(caption: impl Into<String>, lines: I) -> Frame
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Frame{
+            caption: caption.into(),
+            rows: lines.into_iter().map(|line| line.as_ref().chars().collect()).collect(),
+            highlights: Vec::new(),
+        }
+    }
+
+    pub fn highlight(&mut self, x: usize, y: usize, color: Color){
+        self.highlights.push((x, y, color));
+    }
+
+    /// Highlights length cells of a row starting at x
+    pub fn highlight_span(&mut self, x: usize, y: usize, length: usize, color: Color){
+        self.highlights.extend((x..x + length).map(|x| (x, y, color)));
+    }
+
+    fn colors(&self) -> Vec<Vec<Option<Color>>>{
+        let mut colors: Vec<Vec<Option<Color>>> = self.rows.iter().map(|row| vec![None; row.len()]).collect();
+        for (x, y, color) in self.highlights.iter(){
+            if let Some(cell) = colors.get_mut(*y).and_then(|row| row.get_mut(*x)){
+                *cell = Some(*color);
+            }
+        }
+        colors
+    }
+
+    pub fn to_text(&self) -> String{
+        let mut result = format!("{}\n", self.caption);
+        for row in self.rows.iter(){
+            result.extend(row.iter());
+            result.push('\n');
+        }
+        result
+    }
+
+    pub fn to_ansi(&self) -> String{
+        let mut result = format!("\x1b[1m{}\x1b[0m\n", self.caption);
+        for (row, colors) in self.rows.iter().zip(self.colors()){
+            let mut current = None;
+            for (char, color) in row.iter().zip(colors){
+                if color != current{
+                    match color{
+                        Some([r, g, b]) => write!(result, "\x1b[38;2;{};{};{}m", r, g, b).unwrap(),
+                        None => result.push_str("\x1b[0m"),
+                    }
+                    current = color;
+                }
+                result.push(*char);
+            }
+            result.push_str("\x1b[0m\n");
+        }
+        result
+    }
+}
+
+/// Receives the frames emitted by a task
+pub trait Visualizer{
+    /// Called once before a task runs, with a name that is unique per task
+    fn begin(&mut self, _task: &str){}
+    fn frame(&mut self, frame: Frame);
+    /// Called once after a task ran, reports anything that went wrong while handling its frames
+    fn finish(&mut self) -> std::io::Result<()>{
+        Ok(())
+    }
+}
+
+/// Writes every frame to its own file, in a directory per task
+pub struct DirectoryWriter{
+    root: PathBuf,
+    directory: PathBuf,
+    count: usize,
+    error: Option<std::io::Error>,
+}
+
+impl DirectoryWriter{
+    pub fn new(root: PathBuf) -> DirectoryWriter{
+        DirectoryWriter{ directory: root.clone(), root, count: 0, error: None }
+    }
+
+    fn write(&self, frame: &Frame) -> std::io::Result<()>{
+        std::fs::create_dir_all(&self.directory)?;
+        std::fs::write(self.directory.join(format!("frame_{:05}.ansi", self.count)), frame.to_ansi())
+    }
+}
+
+impl Visualizer for DirectoryWriter{
+    fn begin(&mut self, task: &str){
+        self.directory = self.root.join(task);
+        self.count = 0;
+    }
+
+    fn frame(&mut self, frame: Frame){
+        // Keep the first error, the rest of the frames are most likely failing for the same reason
+        if self.error.is_none(){
+            self.error = self.write(&frame).err();
+        }
+        self.count += 1;
+    }
+
+    fn finish(&mut self) -> std::io::Result<()>{
+        self.error.take().map_or(Ok(()), Err)
+    }
+}
+
+/// Plays frames back in the terminal, redrawing the screen for every frame
+pub struct TerminalPlayback{
+    pub delay: Duration,
+}
+
+impl Visualizer for TerminalPlayback{
+    fn frame(&mut self, frame: Frame){
+        let mut stdout = std::io::stdout().lock();
+        // Playback is best effort, a closed terminal should not fail the task
+        if stdout.is_terminal(){
+            let _ = write!(stdout, "\x1b[2J\x1b[H{}", frame.to_ansi()).and_then(|_| stdout.flush());
+            std::thread::sleep(self.delay);
+        } else{
+            // Redirected output gets every frame in sequence without escape codes
+            let _ = write!(stdout, "{}", frame.to_text());
+        }
+    }
+}
+
+thread_local!{
+    static ACTIVE: RefCell<Option<Box<dyn Visualizer>>> = const { RefCell::new(None) };
+}
+
+/// Whether a visualizer is listening, for tasks that need to prepare state for their frames
+pub fn enabled() -> bool{
+    ACTIVE.with_borrow(|active| active.is_some())
+}
+
+/// Passes a frame to the active visualizer. The frame is only built when there is one.
+pub fn emit(build: impl FnOnce() -> Frame){
+    if !enabled(){
+        return;
+    }
+
+    // Built outside of the borrow so building a frame may check enabled() itself
+    let frame = build();
+    ACTIVE.with_borrow_mut(|active|{
+        if let Some(visualizer) = active{
+            visualizer.frame(frame);
+        }
+    });
+}
+
+/// Runs f with the visualizer receiving every frame emitted on this thread, then hands it back
+pub fn with_visualizer<T>(visualizer: Box<dyn Visualizer>, f: impl FnOnce() -> T) -> (T, Box<dyn Visualizer>){
+    let previous = ACTIVE.replace(Some(visualizer));
+    let result = f();
+    let visualizer = ACTIVE.replace(previous).unwrap();
+    (result, visualizer)
+}
+
+/// Collects frames so tests can inspect what a task emitted
+#[cfg(test)]
+#[derive(Default)]
+struct Recorder{
+    frames: std::rc::Rc<RefCell<Vec<Frame>>>,
+}
+
+#[cfg(test)]
+impl Visualizer for Recorder{
+    fn frame(&mut self, frame: Frame){
+        self.frames.borrow_mut().push(frame);
+    }
+}
+
+/// Runs f and returns the frames it emitted
+#[cfg(test)]
+pub fn record<T>(f: impl FnOnce() -> T) -> (T, Vec<Frame>){
+    let recorder = Recorder::default();
+    let frames = recorder.frames.clone();
+    let (result, _) = with_visualizer(Box::new(recorder), f);
+    let frames = frames.take();
+    (result, frames)
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn test_emit_disabled(){
+        assert!(!enabled());
+        emit(|| panic!("frames must not be built without a visualizer"));
+    }
+
+    #[test]
+    fn test_emit_recorded(){
+        let (result, frames) = record(||{
+            assert!(enabled());
+            emit(|| Frame::new("first"));
+            emit(|| Frame::new("second"));
+            5
+        });
+
+        assert_eq!(result, 5);
+        assert_eq!(frames.iter().map(|frame| frame.caption.as_str()).collect::<Vec<_>>(), ["first", "second"]);
+        assert!(!enabled());
+    }
+
+    #[test]
+    fn test_frame_text(){
+        let mut frame = Frame::from_lines("grid", ["ab", "cd"]);
+        frame.highlight(1, 1, GOOD);
+        frame.highlight(5, 5, BAD);
+        assert_eq!(frame.to_text(), "grid\nab\ncd\n");
+        assert_eq!(frame.to_ansi(), "\x1b[1mgrid\x1b[0m\nab\x1b[0m\nc\x1b[38;2;40;200;80md\x1b[0m\n");
+    }
+
+    #[test]
+    fn test_directory_writer(){
+        let root = std::env::temp_dir().join(format!("aoc_visualize_test_{}", std::process::id()));
+        let mut writer = DirectoryWriter::new(root.clone());
+        writer.begin("2023-3-1");
+        writer.frame(Frame::from_lines("first", ["#"]));
+        writer.frame(Frame::from_lines("second", ["."]));
+        writer.finish().unwrap();
+
+        let second = std::fs::read_to_string(root.join("2023-3-1").join("frame_00001.ansi")).unwrap();
+        assert!(second.contains("second"));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
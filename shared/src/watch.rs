@@ -0,0 +1,209 @@
+//! Re-runs the tasks of a day whenever its source or one of its inputs changes. The running binary cannot
+//! pick up its own changes, so the watcher rebuilds through cargo and runs the rebuilt runner with --json:
+//! first the day's tests, then the samples and the real input, showing how every answer differs from the
+//! answer of the previous run.
+//!
+//! Samples are the inputs next to the real one that are named after the day and something else,
+//! like inputs/2023/5.sample.txt for inputs/2023/5.txt.
+
+use std::{collections::{BTreeSet, HashMap}, path::{Path, PathBuf}, process::{Command, ExitStatus, Stdio}, sync::mpsc, time::Duration};
+
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+use crate::TaskReport;
+
+/// Where the inputs of the days are kept
+const INPUT_DIRECTORY: &str = "inputs/2023";
+
+/// Editors save a file in several steps, changes this close together are handled at once
+const SETTLE_TIME: Duration = Duration::from_millis(200);
+
+#[derive(clap::Args)]
+pub struct WatchArgs{
+    /// Build and run with optimizations, for days that are too slow without them
+    #[arg(long)]
+    release: bool,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum WatchError{
+    #[error("there is no {} directory to watch", .0.display())]
+    MissingDirectory(PathBuf),
+    #[error(transparent)]
+    Notify(#[from] notify::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// The day a changed file belongs to: dayN.rs and the files of a dayN module in the source directory,
+/// and N.txt and N.name.txt in the input directory
+fn changed_day(source: &Path, inputs: &Path, path: &Path) -> Option<u32>{
+    if let Ok(relative) = path.strip_prefix(source){
+        let module = relative.components().next()?.as_os_str().to_str()?;
+        let module = if relative.components().count() == 1 { module.strip_suffix(".rs")? } else { module };
+        return module.strip_prefix("day")?.parse().ok();
+    }
+
+    let name = path.strip_prefix(inputs).ok()?.to_str()?;
+    let (day, rest) = name.split_once('.')?;
+    (rest == "txt" || rest.ends_with(".txt")).then(|| day.parse().ok()).flatten()
+}
+
+/// Inputs of a day with their labels, the samples in name order and then the real input
+fn inputs(directory: &Path, day: u32) -> std::io::Result<Vec<(String, PathBuf)>>{
+    let real = format!("{}.txt", day);
+    let prefix = format!("{}.", day);
+    let mut samples = Vec::new();
+    for entry in std::fs::read_dir(directory)?{
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        if let Some(label) = name.strip_prefix(&prefix).and_then(|rest| rest.strip_suffix(".txt")){
+            samples.push((label.to_string(), directory.join(&name)));
+        }
+    }
+    samples.sort();
+
+    let real = directory.join(real);
+    if real.is_file(){
+        samples.push(("input".to_string(), real));
+    }
+    Ok(samples)
+}
+
+/// An answer next to the answer of the previous run on the same input
+fn describe(previous: Option<&str>, answer: &str) -> String{
+    match previous{
+        None => answer.to_string(),
+        Some(previous) if previous == answer => format!("{} (unchanged)", answer),
+        Some(previous) => format!("{} (was {})", answer, previous),
+    }
+}
+
+/// Latest answer of every task on every input
+#[derive(Default)]
+struct Answers(HashMap<(PathBuf, String), String>);
+
+fn cargo(args: &WatchArgs, subcommand: &str) -> Command{
+    let mut command = Command::new(std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into()));
+    // The inputs may be outside of the workspace, so cargo is pointed at it instead of run in it
+    command.args([subcommand, "-q", "--manifest-path", concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml")]);
+    if args.release{
+        command.arg("--release");
+    }
+    command
+}
+
+fn run_input(args: &WatchArgs, day: u32, (label, path): &(String, PathBuf), answers: &mut Answers) -> Result<ExitStatus, WatchError>{
+    let output = cargo(args, "run")
+        .arg("--")
+        .args(["--json", "--day", &day.to_string(), "--input"])
+        .arg(path)
+        .stderr(Stdio::inherit())
+        .output()?;
+
+    for line in String::from_utf8_lossy(&output.stdout).lines(){
+        let Ok(report) = serde_json::from_str::<TaskReport>(line) else { continue };
+        let answer = report.output.trim_end().to_string();
+        let previous = answers.0.insert((path.clone(), report.task.clone()), answer.clone());
+        println!("  {:<10} {:<20} {}", label, report.task, describe(previous.as_deref(), &answer));
+    }
+    Ok(output.status)
+}
+
+fn run_day(args: &WatchArgs, day: u32, answers: &mut Answers) -> Result<(), WatchError>{
+    println!("Day {} changed", day);
+    if !cargo(args, "build").status()?.success(){
+        println!("The build failed, waiting for the next change");
+        return Ok(());
+    }
+
+    if !cargo(args, "test").args(["--", &format!("day{}::", day)]).status()?.success(){
+        println!("Tests of day {} failed", day);
+    }
+
+    for input in inputs(Path::new(INPUT_DIRECTORY), day)?{
+        if !run_input(args, day, &input, answers)?.success(){
+            println!("  {:<10} the run failed", input.0);
+        }
+    }
+    Ok(())
+}
+
+/// Adds the days of the files an event touched
+fn collect_days(days: &mut BTreeSet<u32>, source: &Path, inputs: &Path, event: notify::Result<Event>){
+    match event{
+        Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)) => {
+            days.extend(event.paths.iter().filter_map(|path| changed_day(source, inputs, path)));
+        },
+        Ok(_) => {},
+        Err(error) => eprintln!("Failed to watch: {}", error),
+    }
+}
+
+pub fn watch(args: &WatchArgs) -> Result<(), WatchError>{
+    let source = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+    let inputs = Path::new(INPUT_DIRECTORY);
+    // Events name files by their absolute path
+    let canonical = |directory: &Path| directory.canonicalize().map_err(|_| WatchError::MissingDirectory(directory.to_path_buf()));
+    let (source, inputs) = (canonical(&source)?, canonical(inputs)?);
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    watcher.watch(&source, RecursiveMode::Recursive)?;
+    watcher.watch(&inputs, RecursiveMode::NonRecursive)?;
+    println!("Watching {} and {}", source.display(), inputs.display());
+
+    let mut answers = Answers::default();
+    while let Ok(event) = receiver.recv(){
+        let mut days = BTreeSet::new();
+        collect_days(&mut days, &source, &inputs, event);
+        while let Ok(event) = receiver.recv_timeout(SETTLE_TIME){
+            collect_days(&mut days, &source, &inputs, event);
+        }
+
+        for day in days{
+            run_day(args, day, &mut answers)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn test_changed_day(){
+        let (source, inputs) = (Path::new("/aoc/shared/src"), Path::new("/aoc/inputs/2023"));
+        let day = |path: &str| changed_day(source, inputs, Path::new(path));
+        assert_eq!(day("/aoc/shared/src/day5.rs"), Some(5));
+        assert_eq!(day("/aoc/shared/src/day10/render.rs"), Some(10));
+        assert_eq!(day("/aoc/shared/src/day5.rs~"), None);
+        assert_eq!(day("/aoc/shared/src/main.rs"), None);
+        assert_eq!(day("/aoc/inputs/2023/11.txt"), Some(11));
+        assert_eq!(day("/aoc/inputs/2023/3.sample.txt"), Some(3));
+        assert_eq!(day("/aoc/inputs/2023/3.txt.swp"), None);
+        assert_eq!(day("/aoc/target/day5.rs"), None);
+    }
+
+    #[test]
+    fn test_inputs(){
+        let directory = std::env::temp_dir().join(format!("aoc_watch_test_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        for name in ["1.txt", "1.sample2.txt", "1.sample.txt", "10.txt", "2.sample.txt"]{
+            std::fs::write(directory.join(name), "").unwrap();
+        }
+
+        let labels = |day| inputs(&directory, day).unwrap().into_iter().map(|(label, _)| label).collect::<Vec<_>>();
+        assert_eq!(labels(1), ["sample", "sample2", "input"]);
+        assert_eq!(labels(2), ["sample"]);
+        assert_eq!(labels(3), Vec::<String>::new());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_describe(){
+        assert_eq!(describe(None, "Result: 35"), "Result: 35");
+        assert_eq!(describe(Some("Result: 35"), "Result: 35"), "Result: 35 (unchanged)");
+        assert_eq!(describe(Some("Result: 34"), "Result: 35"), "Result: 35 (was Result: 34)");
+    }
+}